@@ -0,0 +1,68 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+
+const DEFAULT_DELAY_MS: u64 = 200;
+
+// Draws frames in the terminal, or writes them to a file when an output is given
+pub struct Animation {
+    delay: Duration,
+    output: Option<File>,
+    frames: usize,
+}
+
+impl Animation {
+    // Takes the `--delay <ms>` and `--output <file>` options out of the arguments, returning the
+    // animation along with the remaining arguments
+    pub fn from_args(args: &[String]) -> Result<(Self, Vec<String>)> {
+        let mut delay = Duration::from_millis(DEFAULT_DELAY_MS);
+        let mut output = None;
+        let mut rest = Vec::new();
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--delay" => {
+                    let ms = args.next().ok_or(anyhow!("Missing delay"))?.parse()?;
+                    delay = Duration::from_millis(ms);
+                }
+                "--output" => {
+                    let path = args.next().ok_or(anyhow!("Missing output file"))?;
+                    output = Some(File::create(path)?);
+                }
+                _ => rest.push(arg.clone()),
+            }
+        }
+
+        Ok((
+            Self {
+                delay,
+                output,
+                frames: 0,
+            },
+            rest,
+        ))
+    }
+
+    pub fn frame(&mut self, title: &str, frame: &impl Display) -> Result<()> {
+        match &mut self.output {
+            Some(file) => writeln!(file, "{}\n{}\n", title, frame)?,
+            None => {
+                print!("\x1b[2J\x1b[H{}\n{}\n", title, frame);
+                io::stdout().flush()?;
+                thread::sleep(self.delay);
+            }
+        }
+        self.frames += 1;
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> String {
+        format!("Rendered {} frames", self.frames)
+    }
+}
@@ -0,0 +1,221 @@
+use crate::grid::{Coords, Grid, ALL_DIRECTIONS};
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use itertools::Itertools;
+
+pub trait Neighborhood<T> {
+    fn neighbors(&self, grid: &Grid<T>, coords: Coords) -> Vec<Coords>;
+}
+
+pub trait Rule<T> {
+    fn next(&self, cell: &T, neighbors: &[T]) -> T;
+}
+
+// The 8 surrounding cells
+pub struct Adjacent;
+
+impl<T> Neighborhood<T> for Adjacent {
+    fn neighbors(&self, grid: &Grid<T>, coords: Coords) -> Vec<Coords> {
+        grid.neighbors(coords, &ALL_DIRECTIONS).collect()
+    }
+}
+
+// The first cell in each of the 8 directions that can't be seen through
+pub struct LineOfSight<F>(pub F);
+
+impl<T, F: Fn(&T) -> bool> Neighborhood<T> for LineOfSight<F> {
+    fn neighbors(&self, grid: &Grid<T>, coords: Coords) -> Vec<Coords> {
+        let transparent = &self.0;
+        ALL_DIRECTIONS
+            .iter()
+            .filter_map(|direction| {
+                grid.ray(coords, *direction)
+                    .find(|coords| !transparent(&grid[*coords]))
+            })
+            .collect()
+    }
+}
+
+pub struct StepStats<T> {
+    pub changed: usize,
+    pub counts: HashMap<T, usize>,
+}
+
+pub enum Outcome {
+    // No cell changes after the given amount of steps
+    Stable(usize),
+    Cycle { start: usize, period: usize },
+    StepLimit,
+}
+
+pub struct Run<T> {
+    pub outcome: Outcome,
+    pub stats: Vec<StepStats<T>>,
+}
+
+pub struct Automaton<T, R> {
+    grid: Grid<T>,
+    neighbors: Grid<Vec<Coords>>,
+    rule: R,
+}
+
+impl<T, R> Automaton<T, R>
+where
+    T: Copy + Eq + Hash,
+    R: Rule<T>,
+{
+    pub fn new(grid: Grid<T>, neighborhood: &dyn Neighborhood<T>, rule: R) -> Self {
+        let neighbors = Grid::from_fn(grid.width(), grid.height(), |coords| {
+            neighborhood.neighbors(&grid, coords)
+        });
+
+        Self {
+            grid,
+            neighbors,
+            rule,
+        }
+    }
+
+    pub fn grid(&self) -> &Grid<T> {
+        &self.grid
+    }
+
+    pub fn step(&mut self) -> StepStats<T> {
+        let grid = &self.grid;
+        let mut neighbor_cells = Vec::new();
+        let next = Grid::from_fn(grid.width(), grid.height(), |coords| {
+            neighbor_cells.clear();
+            neighbor_cells.extend(
+                self.neighbors
+                    .get(coords)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|neighbor| grid.get(*neighbor))
+                    .copied(),
+            );
+            self.rule.next(&grid[coords], &neighbor_cells)
+        });
+
+        let changed = grid.iter().zip(next.iter()).filter(|(a, b)| a != b).count();
+        self.grid = next;
+
+        StepStats {
+            changed,
+            counts: self.grid.iter().copied().counts(),
+        }
+    }
+
+    pub fn run(&mut self, max_steps: usize) -> Run<T> {
+        let mut seen = HashMap::new();
+        seen.insert(self.grid.clone(), 0);
+
+        let mut stats = Vec::new();
+        for step in 1..=max_steps {
+            let step_stats = self.step();
+            let changed = step_stats.changed;
+            stats.push(step_stats);
+
+            if changed == 0 {
+                return Run {
+                    outcome: Outcome::Stable(step - 1),
+                    stats,
+                };
+            }
+            if let Some(start) = seen.insert(self.grid.clone(), step) {
+                return Run {
+                    outcome: Outcome::Cycle {
+                        start,
+                        period: step - start,
+                    },
+                    stats,
+                };
+            }
+        }
+
+        Run {
+            outcome: Outcome::StepLimit,
+            stats,
+        }
+    }
+}
+
+// Birth/survival rule for cells that are either alive or dead, in B/S notation, e.g. "B3/S23"
+#[derive(Clone)]
+pub struct LifeRule {
+    birth: Vec<usize>,
+    survival: Vec<usize>,
+}
+
+impl LifeRule {
+    pub fn new(birth: &[usize], survival: &[usize]) -> Self {
+        Self {
+            birth: birth.to_vec(),
+            survival: survival.to_vec(),
+        }
+    }
+
+    pub fn conway() -> Self {
+        Self::new(&[3], &[2, 3])
+    }
+
+    // Whether dead cells without any alive neighbors come alive, which makes an infinite space
+    // fill up
+    pub fn births_from_nothing(&self) -> bool {
+        self.birth.contains(&0)
+    }
+
+    pub fn next(&self, alive: bool, alive_neighbors: usize) -> bool {
+        if alive {
+            self.survival.contains(&alive_neighbors)
+        } else {
+            self.birth.contains(&alive_neighbors)
+        }
+    }
+}
+
+impl FromStr for LifeRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // Single digit counts are written together, e.g. "S23", and larger ones separated by
+        // commas, e.g. "S2,3,10"
+        let counts = |part: &str, prefix: char| -> Result<Vec<usize>> {
+            let counts = part
+                .strip_prefix(prefix)
+                .or_else(|| part.strip_prefix(prefix.to_ascii_lowercase()))
+                .ok_or(anyhow!("Expected '{}' in rule '{}'", prefix, s))?;
+            if counts.contains(',') {
+                counts
+                    .split(',')
+                    .map(|n| {
+                        n.parse()
+                            .map_err(|_| anyhow!("Invalid neighbor count '{}'", n))
+                    })
+                    .collect()
+            } else {
+                counts
+                    .chars()
+                    .map(|c| {
+                        c.to_digit(10)
+                            .map(|n| n as usize)
+                            .ok_or(anyhow!("Invalid neighbor count '{}'", c))
+                    })
+                    .collect()
+            }
+        };
+
+        let (birth, survival) = s
+            .split('/')
+            .collect_tuple()
+            .ok_or(anyhow!("Invalid rule '{}'", s))?;
+
+        Ok(Self {
+            birth: counts(birth, 'B')?,
+            survival: counts(survival, 'S')?,
+        })
+    }
+}
@@ -0,0 +1,176 @@
+use crate::random::Random;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+
+// An unsigned integer of any size, stored as base 2^32 digits from least to most significant,
+// without leading zeros
+#[derive(Clone, PartialEq, Eq)]
+pub struct BigUint(Vec<u32>);
+
+impl BigUint {
+    pub fn zero() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn trimmed(mut digits: Vec<u32>) -> Self {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Self(digits)
+    }
+
+    // self * factor + addend
+    fn mul_add_small(&self, factor: u32, addend: u32) -> Self {
+        let mut carry = addend as u64;
+        let mut digits = self
+            .0
+            .iter()
+            .map(|digit| {
+                let value = *digit as u64 * factor as u64 + carry;
+                carry = value >> 32;
+                value as u32
+            })
+            .collect::<Vec<_>>();
+        digits.push(carry as u32);
+        Self::trimmed(digits)
+    }
+
+    // The quotient and remainder of self / divisor
+    fn div_rem_small(&self, divisor: u32) -> (Self, u32) {
+        let mut remainder = 0u64;
+        let mut digits = vec![0; self.0.len()];
+        for (i, digit) in self.0.iter().enumerate().rev() {
+            let value = (remainder << 32) | *digit as u64;
+            digits[i] = (value / divisor as u64) as u32;
+            remainder = value % divisor as u64;
+        }
+        (Self::trimmed(digits), remainder as u32)
+    }
+
+    // A uniformly random number below self, which must not be zero
+    pub fn random_below(&self, random: &mut Random) -> Self {
+        let top = *self.0.last().expect("No numbers below zero");
+        // Only as many bits of the most significant digit as self has are drawn, so that at least
+        // half of the candidates are accepted
+        let mask = u32::MAX >> top.leading_zeros();
+        loop {
+            let mut digits = (0..self.0.len())
+                .map(|_| random.next_u64() as u32)
+                .collect::<Vec<_>>();
+            *digits.last_mut().unwrap() &= mask;
+            let candidate = Self::trimmed(digits);
+            if candidate < *self {
+                return candidate;
+            }
+        }
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(n: u64) -> Self {
+        Self::trimmed(vec![n as u32, (n >> 32) as u32])
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.iter().rev().cmp(other.0.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: Self) -> BigUint {
+        let mut carry = 0;
+        let mut digits = (0..self.0.len().max(other.0.len()))
+            .map(|i| {
+                let value = *self.0.get(i).unwrap_or(&0) as u64
+                    + *other.0.get(i).unwrap_or(&0) as u64
+                    + carry;
+                carry = value >> 32;
+                value as u32
+            })
+            .collect::<Vec<_>>();
+        digits.push(carry as u32);
+        BigUint::trimmed(digits)
+    }
+}
+
+impl Sub for &BigUint {
+    type Output = BigUint;
+
+    fn sub(self, other: Self) -> BigUint {
+        assert!(*self >= *other, "Subtraction overflow");
+
+        let mut borrow = 0;
+        let digits = self
+            .0
+            .iter()
+            .enumerate()
+            .map(|(i, digit)| {
+                let value = *digit as i64 - *other.0.get(i).unwrap_or(&0) as i64 - borrow;
+                borrow = if value < 0 { 1 } else { 0 };
+                (value + (borrow << 32)) as u32
+            })
+            .collect();
+        BigUint::trimmed(digits)
+    }
+}
+
+impl FromStr for BigUint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            bail!("Empty number");
+        }
+
+        s.chars()
+            .try_fold(Self::zero(), |n, c| match c.to_digit(10) {
+                Some(digit) => Ok(n.mul_add_small(10, digit)),
+                None => bail!("Invalid digit '{}' in '{}'", c, s),
+            })
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Groups of 9 decimal digits, from least to most significant
+        let mut groups = Vec::new();
+        let mut n = self.clone();
+        while !n.is_zero() {
+            let (quotient, remainder) = n.div_rem_small(1_000_000_000);
+            groups.push(remainder);
+            n = quotient;
+        }
+
+        match groups.split_last() {
+            None => write!(f, "0"),
+            Some((first, rest)) => {
+                write!(f, "{}", first)?;
+                rest.iter()
+                    .rev()
+                    .try_for_each(|group| write!(f, "{:09}", group))
+            }
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::io::BufRead;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 
 pub mod day1;
 pub mod day10;
@@ -39,4 +39,8 @@ pub struct Day {
 pub trait Solver {
     fn part1(&self) -> Result<String>;
     fn part2(&self) -> Result<String>;
+
+    fn command(&self, name: &str, _args: &[String]) -> Result<String> {
+        bail!("Unknown command '{}'", name)
+    }
 }
@@ -1,59 +1,76 @@
 use crate::day::{Day, DynSolver, Solver};
+use crate::sums::k_sum;
 
 use std::io::{BufRead, BufReader};
 
-use anyhow::{bail, Result};
-use itertools::iproduct;
+use anyhow::{anyhow, bail, Result};
+use itertools::Itertools;
+
+const TARGET: u64 = 2020;
 
 pub const DAY1: Day = Day {
     title: "Report Repair",
     solver_from_input,
 };
 
-struct Day1Solver(Vec<u32>);
+struct Day1Solver(Vec<u64>);
 impl Solver for Day1Solver {
     fn part1(&self) -> Result<String> {
-        let numbers = &self.0;
-        let mut low = 0usize;
-        let mut high = numbers.len() - 1;
-
-        while low < high {
-            let n1 = numbers[low];
-            let n2 = numbers[high];
-            let sum = n1 + n2;
-            if sum < 2020 {
-                low += 1;
-            } else if sum > 2020 {
-                high -= 1;
-            } else {
-                return Ok(format!("Solution: {}", n1 * n2));
-            }
-        }
+        let product = self.product_of_entries(2, TARGET)?;
 
-        bail!("Could not find a solution")
+        Ok(format!("Solution: {}", product))
     }
 
     fn part2(&self) -> Result<String> {
-        let numbers = &self.0;
-        let indices = 0..numbers.len();
-        for (a, b, c) in iproduct!(indices.clone(), indices.clone(), indices) {
-            if numbers[a] + numbers[b] + numbers[c] == 2020 {
-                return Ok(format!(
-                    "Solution: {}",
-                    numbers[a] * numbers[b] * numbers[c]
-                ));
+        let product = self.product_of_entries(3, TARGET)?;
+
+        Ok(format!("Solution: {}", product))
+    }
+
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "sum" => {
+                let k = args
+                    .first()
+                    .ok_or(anyhow!("Missing amount of entries"))?
+                    .parse()?;
+                if k == 0 {
+                    bail!("At least one entry is needed");
+                }
+                let target = args.get(1).map_or(Ok(TARGET), |s| s.parse())?;
+
+                let indices = k_sum(&self.0, k, target).ok_or(anyhow!(
+                    "No {} entries add up to {}",
+                    k,
+                    target
+                ))?;
+                let entries = indices.iter().map(|i| self.0[*i]).collect_vec();
+
+                Ok(format!(
+                    "{} = {}\nProduct: {}",
+                    entries.iter().join(" + "),
+                    target,
+                    entries.iter().product::<u64>()
+                ))
             }
+            _ => bail!("Unknown command '{}'", name),
         }
+    }
+}
+
+impl Day1Solver {
+    // The product of k entries adding up to the target
+    fn product_of_entries(&self, k: usize, target: u64) -> Result<u64> {
+        let indices = k_sum(&self.0, k, target).ok_or(anyhow!("Could not find a solution"))?;
 
-        bail!("Could not find a solution")
+        Ok(indices.iter().map(|i| self.0[*i]).product())
     }
 }
 
 fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
-    let mut numbers = BufReader::new(input)
+    let numbers = BufReader::new(input)
         .lines()
-        .map(|line| -> Result<u32> { Ok(line?.parse()?) })
+        .map(|line| -> Result<u64> { Ok(line?.parse()?) })
         .collect::<Result<Vec<_>, _>>()?;
-    numbers.sort();
     Ok(Box::new(Day1Solver(numbers)))
 }
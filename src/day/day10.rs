@@ -1,79 +1,201 @@
+use crate::bigint::BigUint;
 use crate::day::{Day, DynSolver, Solver};
+use crate::random::Random;
 
-use std::collections::HashMap;
 use std::io::BufRead;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use itertools::Itertools;
 
+const MAX_DIFFERENCE: u32 = 3;
+
 pub const DAY10: Day = Day {
     title: "Adapter Array",
     solver_from_input,
 };
 
-struct Day10Solver(Vec<u32>);
+struct AdapterChain {
+    // Every rating in order, starting with the outlet and ending with the device
+    ratings: Vec<u32>,
+    // The number of ways to get from every rating to the device
+    arrangements: Vec<BigUint>,
+}
+
+impl AdapterChain {
+    fn new(mut adapters: Vec<u32>) -> Result<Self> {
+        adapters.sort_unstable();
+        let device = adapters.last().ok_or(anyhow!("No adapters"))? + MAX_DIFFERENCE;
+        let ratings = [0]
+            .iter()
+            .chain(&adapters)
+            .chain(&[device])
+            .copied()
+            .collect_vec();
+
+        for (a, b) in ratings.iter().tuple_windows() {
+            if a == b {
+                bail!("Multiple adapters rated {} jolts", a);
+            }
+            if b - a > MAX_DIFFERENCE {
+                bail!("Nothing fits between {} and {} jolts", a, b);
+            }
+        }
+
+        let mut arrangements = vec![BigUint::zero(); ratings.len()];
+        arrangements[ratings.len() - 1] = BigUint::from(1);
+        for i in (0..(ratings.len() - 1)).rev() {
+            arrangements[i] = (i + 1..ratings.len())
+                .take_while(|j| ratings[*j] - ratings[i] <= MAX_DIFFERENCE)
+                .fold(BigUint::zero(), |sum, j| &sum + &arrangements[j]);
+        }
+
+        Ok(Self {
+            ratings,
+            arrangements,
+        })
+    }
+
+    // How many times every difference in jolts occurs when using every adapter
+    fn histogram(&self) -> Vec<usize> {
+        let mut histogram = vec![0; MAX_DIFFERENCE as usize + 1];
+        for (a, b) in self.ratings.iter().tuple_windows() {
+            histogram[(b - a) as usize] += 1;
+        }
+        histogram
+    }
+
+    fn count_arrangements(&self) -> &BigUint {
+        &self.arrangements[0]
+    }
+
+    // The arrangement at the given index, when ordered by the ratings of the adapters used
+    fn arrangement(&self, index: &BigUint) -> Result<Vec<u32>> {
+        if index >= self.count_arrangements() {
+            bail!(
+                "Index {} out of range, there are {} arrangements",
+                index,
+                self.count_arrangements()
+            );
+        }
+
+        let mut index = index.clone();
+        let mut current = 0;
+        let mut arrangement = vec![self.ratings[0]];
+        while current < self.ratings.len() - 1 {
+            // Skips past every arrangement using an earlier next adapter
+            current = (current + 1..self.ratings.len())
+                .find(|next| {
+                    if index < self.arrangements[*next] {
+                        true
+                    } else {
+                        index = &index - &self.arrangements[*next];
+                        false
+                    }
+                })
+                .unwrap();
+            arrangement.push(self.ratings[current]);
+        }
+
+        Ok(arrangement)
+    }
+}
+
+// Written like in the puzzle, with the outlet and device in parentheses
+fn format_arrangement(arrangement: &[u32]) -> String {
+    let last = arrangement.len() - 1;
+    arrangement
+        .iter()
+        .enumerate()
+        .map(|(i, rating)| {
+            if i == 0 || i == last {
+                format!("({})", rating)
+            } else {
+                rating.to_string()
+            }
+        })
+        .join(", ")
+}
+
+struct Day10Solver(AdapterChain);
 impl Solver for Day10Solver {
     fn part1(&self) -> Result<String> {
-        let (ones, threes) = self.0.iter().tuple_windows().map(|(a, b)| b - a).fold(
-            (0, 0),
-            |(ones, threes), diff| match diff {
-                1 => (ones + 1, threes),
-                3 => (ones, threes + 1),
-                _ => (ones, threes),
-            },
-        );
-
-        Ok(format!("Result: {}", ones * threes))
+        let histogram = self.0.histogram();
+
+        Ok(format!("Result: {}", histogram[1] * histogram[3]))
     }
 
     fn part2(&self) -> Result<String> {
-        fn count_arrangements(ratings: &[u32], counted: &mut HashMap<u32, u64>) -> u64 {
-            if ratings.len() == 1 {
-                return 1;
-            }
+        Ok(format!(
+            "Possible adapter arrangements: {}",
+            self.0.count_arrangements()
+        ))
+    }
 
-            let current_rating = ratings[0];
-            if let Some(count) = counted.get(&current_rating) {
-                return *count;
-            }
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "histogram" => Ok(self
+                .0
+                .histogram()
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(difference, count)| format!("{}-jolt differences: {}", difference, count))
+                .join("\n")),
+            "enumerate" => {
+                let limit = args.first().map_or(Ok(10), |s| s.parse::<u64>())?;
+                let total = self.0.count_arrangements();
 
-            let count = (1..ratings.len())
-                .take_while(|index| {
-                    let rating = ratings[*index];
-                    let diff = rating - current_rating;
-                    diff >= 1 && diff <= 3
-                })
-                .map(|index| count_arrangements(&ratings[index..], counted))
-                .sum();
-            counted.insert(current_rating, count);
+                let mut arrangements = Vec::new();
+                let mut index = BigUint::zero();
+                while index < *total && (arrangements.len() as u64) < limit {
+                    arrangements.push(format_arrangement(&self.0.arrangement(&index)?));
+                    index = &index + &BigUint::from(1);
+                }
 
-            count
-        }
+                Ok(format!(
+                    "{}\n{} of {} arrangements",
+                    arrangements.join("\n"),
+                    arrangements.len(),
+                    total
+                ))
+            }
+            "arrangement" => {
+                let index = args
+                    .first()
+                    .ok_or(anyhow!("Missing index"))?
+                    .parse::<BigUint>()?;
 
-        let ratings = self.0.iter().map(|n| *n).collect::<Vec<_>>();
-        let arrangements = count_arrangements(&ratings, &mut HashMap::new());
+                Ok(format_arrangement(&self.0.arrangement(&index)?))
+            }
+            "sample" => {
+                let count = args.first().map_or(Ok(1), |s| s.parse())?;
+                let mut random = match args.get(1) {
+                    Some(seed) => Random::new(seed.parse()?),
+                    None => Random::from_time(),
+                };
 
-        Ok(format!("Possible adapter arrangements: {}", arrangements))
+                (0..count)
+                    .map(|_| {
+                        let index = self.0.count_arrangements().random_below(&mut random);
+                        Ok(format!(
+                            "{}: {}",
+                            index,
+                            format_arrangement(&self.0.arrangement(&index)?)
+                        ))
+                    })
+                    .collect::<Result<Vec<_>>>()
+                    .map(|samples| samples.join("\n"))
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
     }
 }
 
 fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
-    let mut largest_rating = 0;
-    let mut adapters = input
+    let adapters = input
         .lines()
-        .map(|line| -> Result<u32> {
-            let rating = line?.parse()?;
-            if rating > largest_rating {
-                largest_rating = rating;
-            }
-
-            Ok(rating)
-        })
-        .collect::<Result<Vec<_>>>()?;
-
-    adapters.push(0);
-    adapters.sort();
-    adapters.push(largest_rating + 3);
+        .map(|line| -> Result<u32> { Ok(line?.parse()?) })
+        .collect::<Result<_>>()?;
 
-    Ok(Box::new(Day10Solver(adapters)))
+    Ok(Box::new(Day10Solver(AdapterChain::new(adapters)?)))
 }
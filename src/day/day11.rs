@@ -1,25 +1,30 @@
+use crate::animation::Animation;
+use crate::automaton::{Adjacent, Automaton, LineOfSight, Neighborhood, Outcome, Rule, Run};
 use crate::day::{Day, DynSolver, Solver};
+use crate::grid::{Cell, Grid};
+use crate::image::{Color, Image, ToColor};
 
-use std::collections::HashMap;
-use std::convert::TryInto;
 use std::io::BufRead;
-use std::{iter, mem};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+use itertools::Itertools;
+
+const MAX_STEPS: usize = 1000;
+const SCALE: usize = 8;
 
 pub const DAY11: Day = Day {
     title: "Seating System",
     solver_from_input,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Tile {
     Floor,
     Free,
     Occupied,
 }
 
-impl Tile {
+impl Cell for Tile {
     fn from_char(c: char) -> Option<Self> {
         match c {
             '.' => Some(Self::Floor),
@@ -28,187 +33,197 @@ impl Tile {
             _ => None,
         }
     }
-}
 
-#[derive(Clone, Copy, PartialEq)]
-enum Direction {
-    Pos,
-    Neg,
-    None,
+    fn to_char(&self) -> char {
+        match self {
+            Self::Floor => '.',
+            Self::Free => 'L',
+            Self::Occupied => '#',
+        }
+    }
 }
 
-impl Direction {
-    const ALL: [(Self, Self); 8] = [
-        (Self::None, Self::Neg),
-        (Self::Pos, Self::Neg),
-        (Self::Pos, Self::None),
-        (Self::Pos, Self::Pos),
-        (Self::None, Self::Pos),
-        (Self::Neg, Self::Pos),
-        (Self::Neg, Self::None),
-        (Self::Neg, Self::Neg),
-    ];
-
-    fn sign(&self) -> isize {
+impl ToColor for Tile {
+    fn color(&self) -> Color {
         match self {
-            Self::Pos => 1,
-            Self::Neg => -1,
-            Self::None => 0,
+            Self::Floor => Color(120, 120, 120),
+            Self::Free => Color(60, 180, 75),
+            Self::Occupied => Color(200, 40, 40),
         }
     }
 }
 
-#[derive(Clone)]
-struct Map {
-    width: usize,
-    tiles: Vec<Tile>,
-    previous_tiles: Vec<Tile>,
+struct SeatingRule {
+    occupied_tolerance: usize,
 }
 
-impl Map {
-    fn new(width: usize, tiles: Vec<Tile>) -> Self {
-        let next_tiles = tiles.clone();
-        Self {
-            width,
-            tiles,
-            previous_tiles: next_tiles,
-        }
-    }
-
-    fn step(&mut self, adjacents: &HashMap<usize, Vec<usize>>, occupied_tolerance: usize) {
-        for (i, tile) in self.tiles.iter().enumerate() {
-            let adjacent_occupied = |i| {
-                let adjacents = match adjacents.get(&i) {
-                    Some(adjacents) => adjacents.iter(),
-                    None => return 0,
-                };
-
-                adjacents
-                    .filter(|adjacent_index| {
-                        if let Some(Tile::Occupied) = self.tiles.get(**adjacent_index) {
-                            true
-                        } else {
-                            false
-                        }
-                    })
-                    .count()
-            };
-
-            let new_tile = match tile {
-                Tile::Floor => continue,
-                Tile::Free => {
-                    if adjacent_occupied(i) == 0 {
-                        Tile::Occupied
-                    } else {
-                        Tile::Free
-                    }
-                }
-                Tile::Occupied => {
-                    if adjacent_occupied(i) >= occupied_tolerance {
-                        Tile::Free
-                    } else {
-                        Tile::Occupied
-                    }
-                }
-            };
+impl Rule<Tile> for SeatingRule {
+    fn next(&self, tile: &Tile, neighbors: &[Tile]) -> Tile {
+        let occupied = neighbors
+            .iter()
+            .filter(|neighbor| **neighbor == Tile::Occupied)
+            .count();
 
-            self.previous_tiles[i] = new_tile;
+        match tile {
+            Tile::Free if occupied == 0 => Tile::Occupied,
+            Tile::Occupied if occupied >= self.occupied_tolerance => Tile::Free,
+            tile => *tile,
         }
-
-        mem::swap(&mut self.tiles, &mut self.previous_tiles);
     }
+}
 
-    fn scan(&self, index: usize, x: Direction, y: Direction) -> impl Iterator<Item = usize> {
-        let width = self.width as isize;
-        iter::successors(Some(index), move |prev| {
-            let prev = *prev as isize;
-            if x == Direction::Pos && prev % width == width - 1 {
-                return None;
-            }
-            if x == Direction::Neg && prev % width == 0 {
-                return None;
-            }
+struct Day11Solver(Grid<Tile>);
+
+impl Day11Solver {
+    fn simulate(
+        &self,
+        neighborhood: &dyn Neighborhood<Tile>,
+        occupied_tolerance: usize,
+        max_steps: usize,
+    ) -> (Automaton<Tile, SeatingRule>, Run<Tile>) {
+        let mut automaton = Automaton::new(
+            self.0.clone(),
+            neighborhood,
+            SeatingRule { occupied_tolerance },
+        );
+        let run = automaton.run(max_steps);
+
+        (automaton, run)
+    }
 
-            (prev + x.sign() + y.sign() * width).try_into().ok()
-        })
-        .skip(1)
+    fn occupied_when_stable(
+        &self,
+        neighborhood: &dyn Neighborhood<Tile>,
+        occupied_tolerance: usize,
+    ) -> Result<usize> {
+        let (automaton, run) = self.simulate(neighborhood, occupied_tolerance, MAX_STEPS);
+        match run.outcome {
+            Outcome::Stable(_) => Ok(automaton
+                .grid()
+                .iter()
+                .filter(|tile| **tile == Tile::Occupied)
+                .count()),
+            Outcome::Cycle { start, period } => bail!(
+                "Seating never stabilizes, repeating every {} steps from step {}",
+                period,
+                start
+            ),
+            Outcome::StepLimit => bail!("Seating did not stabilize in {} steps", MAX_STEPS),
+        }
     }
 }
 
-struct Day11Solver(Map);
 impl Solver for Day11Solver {
     fn part1(&self) -> Result<String> {
-        let mut map = self.0.clone();
-        let adjacent_tiles = (0..map.tiles.len())
-            .map(|i| {
-                (
-                    i,
-                    Direction::ALL
-                        .iter()
-                        .filter_map(|(x, y)| map.scan(i, *x, *y).next())
-                        .collect(),
-                )
-            })
-            .collect();
-        loop {
-            map.step(&adjacent_tiles, 4);
-            if map.tiles == map.previous_tiles {
-                break;
-            }
-        }
-        let occupied_seats = map
-            .tiles
-            .into_iter()
-            .filter(|tile| *tile == Tile::Occupied)
-            .count();
+        let occupied_seats = self.occupied_when_stable(&Adjacent, 4)?;
 
         Ok(format!("Occupied seats: {}", occupied_seats))
     }
 
     fn part2(&self) -> Result<String> {
-        let mut map = self.0.clone();
-
-        let adjacent_seats = (0..map.tiles.len())
-            .map(|i| {
-                let map = &map;
-                (
-                    i,
-                    Direction::ALL
-                        .iter()
-                        .filter_map(|(x, y)| {
-                            map.scan(i, *x, *y)
-                                .skip_while(|i| map.tiles.get(*i) == Some(&Tile::Floor))
-                                .next()
-                        })
-                        .collect::<Vec<_>>(),
-                )
-            })
-            .collect();
-        loop {
-            map.step(&adjacent_seats, 5);
-            if map.tiles == map.previous_tiles {
-                break;
-            }
-        }
-        let occupied_seats = map
-            .tiles
-            .into_iter()
-            .filter(|tile| *tile == Tile::Occupied)
-            .count();
+        let occupied_seats = self.occupied_when_stable(&line_of_sight(), 5)?;
 
         Ok(format!("Occupied seats: {}", occupied_seats))
     }
+
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "simulate" => {
+                let (neighborhood, occupied_tolerance) = parse_simulation_args(args)?;
+                let max_steps = args.get(2).map_or(Ok(MAX_STEPS), |s| s.parse())?;
+
+                let (automaton, run) =
+                    self.simulate(neighborhood.as_ref(), occupied_tolerance, max_steps);
+                let mut report = run
+                    .stats
+                    .iter()
+                    .enumerate()
+                    .map(|(i, stats)| {
+                        format!(
+                            "Step {}: {} changed, {}",
+                            i + 1,
+                            stats.changed,
+                            stats
+                                .counts
+                                .iter()
+                                .map(|(tile, count)| format!("{} {}", tile.to_char(), count))
+                                .sorted()
+                                .join(", ")
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let occupied = automaton
+                    .grid()
+                    .iter()
+                    .filter(|tile| **tile == Tile::Occupied)
+                    .count();
+                report.push(match run.outcome {
+                    Outcome::Stable(steps) => {
+                        format!("Stable after {} steps, {} occupied", steps, occupied)
+                    }
+                    Outcome::Cycle { start, period } => format!(
+                        "Cycle of period {} from step {}, {} occupied",
+                        period, start, occupied
+                    ),
+                    Outcome::StepLimit => {
+                        format!("Stopped after {} steps, {} occupied", max_steps, occupied)
+                    }
+                });
+
+                Ok(report.join("\n"))
+            }
+            "animate" => {
+                let (mut animation, args) = Animation::from_args(args)?;
+                let (neighborhood, occupied_tolerance) = parse_simulation_args(&args)?;
+
+                let mut automaton = Automaton::new(
+                    self.0.clone(),
+                    neighborhood.as_ref(),
+                    SeatingRule { occupied_tolerance },
+                );
+                animation.frame("Step 0", automaton.grid())?;
+                for step in 1..=MAX_STEPS {
+                    if automaton.step().changed == 0 {
+                        break;
+                    }
+                    animation.frame(&format!("Step {}", step), automaton.grid())?;
+                }
+
+                Ok(animation.finish())
+            }
+            "export" => {
+                let path = args.first().ok_or(anyhow!("Missing output file"))?;
+                let (neighborhood, occupied_tolerance) = parse_simulation_args(&args[1..])?;
+
+                let (automaton, _) =
+                    self.simulate(neighborhood.as_ref(), occupied_tolerance, MAX_STEPS);
+                Image::from_grid(automaton.grid(), SCALE, |_, tile| tile.color()).save(path)?;
+
+                Ok(format!("Saved seating layout to {}", path))
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
+    }
+}
+
+fn parse_simulation_args(args: &[String]) -> Result<(Box<dyn Neighborhood<Tile>>, usize)> {
+    let neighborhood: Box<dyn Neighborhood<Tile>> = match args.first().map(|s| s.as_str()) {
+        Some("adjacent") => Box::new(Adjacent),
+        Some("sight") => Box::new(line_of_sight()),
+        _ => bail!("Expected neighborhood 'adjacent' or 'sight'"),
+    };
+    let occupied_tolerance = args
+        .get(1)
+        .ok_or(anyhow!("Missing occupied tolerance"))?
+        .parse()?;
+
+    Ok((neighborhood, occupied_tolerance))
+}
+
+fn line_of_sight() -> LineOfSight<fn(&Tile) -> bool> {
+    LineOfSight(|tile| *tile == Tile::Floor)
 }
 
 fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
-    let mut lines = input.lines().filter_map(|r| r.ok());
-    let first_line = lines.next().ok_or(anyhow!("No lines"))?;
-    let width = first_line.len();
-    let tiles = iter::once(first_line)
-        .chain(lines)
-        .flat_map(|line| line.chars().collect::<Vec<_>>().into_iter())
-        .filter_map(|c| Tile::from_char(c))
-        .collect();
-
-    Ok(Box::new(Day11Solver(Map::new(width, tiles))))
+    Ok(Box::new(Day11Solver(Grid::parse(input)?)))
 }
@@ -1,10 +1,10 @@
 use crate::day::{Day, DynSolver, Solver};
 
-use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::io::BufRead;
-use std::iter;
+use std::mem;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 
 pub const DAY15: Day = Day {
     title: "Rambunctious Recitation",
@@ -12,62 +12,104 @@ pub const DAY15: Day = Day {
 };
 
 struct Game {
-    starting_numbers: Vec<u32>,
-    previously_spoken: HashMap<u32, usize>,
+    // The number spoken on the last turn
+    last: u32,
+    distinct: usize,
+    // The most turns between a number being spoken and spoken again, along with that number
+    largest_gap: Option<(u32, u32)>,
 }
 
 impl Game {
-    fn new(starting_numbers: &[u32]) -> Self {
-        let previously_spoken = starting_numbers
-            .iter()
-            .take(starting_numbers.len() - 1)
-            .enumerate()
-            .map(|(i, n)| (*n, i))
-            .collect::<HashMap<_, _>>();
-        Self {
-            starting_numbers: starting_numbers.to_vec(),
-            previously_spoken,
+    // Plays the given amount of turns, keeping the turn every number was last spoken on in a vector
+    // indexed by the number. Apart from the starting numbers, no number spoken can be as large as
+    // the amount of turns, so the vector never has to grow
+    fn play(starting_numbers: &[u32], turns: usize) -> Result<Self> {
+        if starting_numbers.is_empty() {
+            bail!("No starting numbers");
         }
-    }
+        if turns == 0 {
+            bail!("The game needs at least one turn");
+        }
+        let turns = u32::try_from(turns)?;
+        let largest_start = *starting_numbers.iter().max().unwrap() as usize;
 
-    fn previously_spoken(&self, previous: u32) -> Option<usize> {
-        self.previously_spoken.get(&previous).copied()
-    }
+        // Turns are counted from 1, so that 0 means never spoken
+        let mut last_spoken = vec![0u32; (turns as usize).max(largest_start + 1)];
+        let mut game = Self {
+            last: starting_numbers[0],
+            distinct: 0,
+            largest_gap: None,
+        };
+        for turn in 2..=turns {
+            let previous_turn = turn - 1;
+            let previous = game.last;
+            let spoken_before = mem::replace(&mut last_spoken[previous as usize], previous_turn);
 
-    fn simulate(mut self) -> impl Iterator<Item = u32> {
-        let mut previous_turn = self.starting_numbers.len() - 1;
-        iter::successors(self.starting_numbers.last().copied(), move |previous| {
-            let next_number =
-                previous_turn - self.previously_spoken(*previous).unwrap_or(previous_turn);
-            self.previously_spoken.insert(*previous, previous_turn);
-            previous_turn += 1;
+            let gap = if spoken_before == 0 {
+                game.distinct += 1;
+                0
+            } else {
+                previous_turn - spoken_before
+            };
+            if gap > game.largest_gap.map_or(0, |(largest, _)| largest) {
+                game.largest_gap = Some((gap, previous));
+            }
+
+            game.last = match starting_numbers.get(turn as usize - 1) {
+                Some(n) => *n,
+                None => gap,
+            };
+        }
+        // The number spoken on the final turn is never looked up by a following turn
+        match last_spoken[game.last as usize] {
+            0 => game.distinct += 1,
+            spoken_before => {
+                let gap = turns - spoken_before;
+                if gap > game.largest_gap.map_or(0, |(largest, _)| largest) {
+                    game.largest_gap = Some((gap, game.last));
+                }
+            }
+        }
 
-            Some(next_number as u32)
-        })
+        Ok(game)
     }
 }
 
 struct Day15Solver(Vec<u32>);
 impl Solver for Day15Solver {
     fn part1(&self) -> Result<String> {
-        let game = Game::new(&self.0);
-        let result = game
-            .simulate()
-            .nth(2020 - self.0.len())
-            .ok_or(anyhow!("No 2020th number"))?;
+        let result = Game::play(&self.0, 2020)?.last;
 
         Ok(format!("The 2020th number spoken is {}", result))
     }
 
     fn part2(&self) -> Result<String> {
-        let game = Game::new(&self.0);
-        let result = game
-            .simulate()
-            .nth(30000000 - self.0.len())
-            .ok_or(anyhow!("No 30000000th number"))?;
+        let result = Game::play(&self.0, 30000000)?.last;
 
         Ok(format!("The 30000000th number spoken is {}", result))
     }
+
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "play" => {
+                let turns = args
+                    .first()
+                    .ok_or(anyhow!("Missing amount of turns"))?
+                    .parse()?;
+                let game = Game::play(&self.0, turns)?;
+
+                let largest_gap = match game.largest_gap {
+                    Some((gap, number)) => format!("{} turns between saying {} twice", gap, number),
+                    None => "no number repeated".to_string(),
+                };
+                Ok(format!(
+                    "Number spoken on turn {}: {}\nDistinct numbers spoken: {}\nLargest gap: {}",
+                    turns, game.last, game.distinct, largest_gap
+                ))
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
+    }
 }
 
 fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
@@ -1,11 +1,12 @@
 use crate::day::{Day, DynSolver, Solver};
+use crate::matching::Matching;
 
-use std::collections::{HashMap, HashSet};
 use std::io::BufRead;
+use std::iter;
 use std::ops::RangeInclusive;
 use std::str::FromStr;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use itertools::Itertools;
 
 pub const DAY16: Day = Day {
@@ -27,17 +28,148 @@ impl FromStr for Ticket {
     }
 }
 
+struct Rule {
+    name: String,
+    ranges: Vec<RangeInclusive<u32>>,
+}
+
+impl Rule {
+    fn contains(&self, value: u32) -> bool {
+        self.ranges.iter().any(|range| range.contains(&value))
+    }
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+
+    // e.g. "departure location: 25-80 or 90-961"
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, ranges) = s
+            .split(": ")
+            .collect_tuple()
+            .ok_or(anyhow!("Invalid rule '{}'", s))?;
+        let ranges = ranges
+            .split(" or ")
+            .map(|range| {
+                let (low, high) = range
+                    .split('-')
+                    .collect_tuple()
+                    .ok_or(anyhow!("Invalid range '{}'", range))?;
+                Ok(low.parse()?..=high.parse()?)
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            name: name.to_string(),
+            ranges,
+        })
+    }
+}
+
 struct TicketRules {
-    rules: HashMap<String, (RangeInclusive<u32>, RangeInclusive<u32>)>,
+    rules: Vec<Rule>,
 }
 
 impl TicketRules {
     fn validate_ticket<'a>(&'a self, ticket: &'a Ticket) -> impl Iterator<Item = u32> + 'a {
-        ticket.0.iter().copied().filter(move |value| {
-            self.rules
-                .values()
-                .all(|(range1, range2)| !range1.contains(value) && !range2.contains(value))
-        })
+        ticket
+            .0
+            .iter()
+            .copied()
+            .filter(move |value| self.rules.iter().all(|rule| !rule.contains(*value)))
+    }
+}
+
+struct Report<'a> {
+    // Every nearby ticket along with its invalid values
+    tickets: Vec<(&'a Ticket, Vec<u32>)>,
+    candidates: Vec<(usize, Vec<&'a str>)>,
+    my_ticket: Result<Vec<(&'a str, u32)>>,
+}
+
+impl Report<'_> {
+    fn text(&self) -> String {
+        let tickets = self
+            .tickets
+            .iter()
+            .enumerate()
+            .map(|(i, (ticket, invalid))| {
+                let values = ticket.0.iter().join(",");
+                if invalid.is_empty() {
+                    format!("{}: {}", i + 1, values)
+                } else {
+                    format!(
+                        "{}: {} (invalid: {})",
+                        i + 1,
+                        values,
+                        invalid.iter().join(", ")
+                    )
+                }
+            })
+            .join("\n");
+        let candidates = self
+            .candidates
+            .iter()
+            .map(|(position, names)| format!("{}: {}", position, names.iter().join(", ")))
+            .join("\n");
+        let my_ticket = match &self.my_ticket {
+            Ok(fields) => fields
+                .iter()
+                .map(|(name, value)| format!("{}: {}", name, value))
+                .join("\n"),
+            Err(e) => format!("Unable to decode: {}", e),
+        };
+
+        format!(
+            "Nearby tickets:\n{}\n\nFields each position could be:\n{}\n\nMy ticket:\n{}",
+            tickets, candidates, my_ticket
+        )
+    }
+
+    // Three tables separated by an empty line: the nearby tickets, the candidate fields of every
+    // position and my decoded ticket
+    fn csv(&self) -> String {
+        let tickets = self
+            .tickets
+            .iter()
+            .enumerate()
+            .map(|(i, (ticket, invalid))| {
+                format!(
+                    "{},{},{}",
+                    i + 1,
+                    csv_field(&ticket.0.iter().join(",")),
+                    csv_field(&invalid.iter().join(","))
+                )
+            });
+        let candidates = self.candidates.iter().map(|(position, names)| {
+            format!("{},{}", position, csv_field(&names.iter().join(",")))
+        });
+        let my_ticket = match &self.my_ticket {
+            Ok(fields) => fields
+                .iter()
+                .map(|(name, value)| format!("{},{}", csv_field(name), value))
+                .collect_vec(),
+            Err(e) => vec![format!(
+                "{},",
+                csv_field(&format!("Unable to decode: {}", e))
+            )],
+        };
+
+        iter::once("ticket,values,invalid values".to_string())
+            .chain(tickets)
+            .chain(iter::once("\nposition,candidates".to_string()))
+            .chain(candidates)
+            .chain(iter::once("\nfield,value".to_string()))
+            .chain(my_ticket)
+            .join("\n")
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(&[',', '"', '\n'][..]) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
     }
 }
 
@@ -58,98 +190,162 @@ impl Solver for Day16Solver {
     }
 
     fn part2(&self) -> Result<String> {
-        let mut field_mapping = vec![self.rules.rules.keys().collect::<HashSet<_>>(); 20];
-        let valid_tickets = self
-            .nearby_tickets
-            .iter()
-            .filter(|ticket| self.rules.validate_ticket(ticket).next().is_none());
+        let product = self.product_of_fields("departure")?;
 
-        for ticket in valid_tickets {
-            for (i, value) in ticket.0.iter().enumerate() {
-                let invalid_field_names = self
-                    .rules
-                    .rules
-                    .iter()
-                    .filter(|(_, (range1, range2))| {
-                        !range1.contains(value) && !range2.contains(value)
-                    })
-                    .map(|(key, _)| key);
+        Ok(format!("Product of departure fields: {}", product))
+    }
 
-                for field_name in invalid_field_names {
-                    field_mapping[i].remove(&field_name);
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "product" => {
+                let prefix = args.first().ok_or(anyhow!("Missing field name prefix"))?;
+                let product = self.product_of_fields(prefix)?;
+
+                Ok(format!(
+                    "Product of fields starting with '{}': {}",
+                    prefix, product
+                ))
+            }
+            "report" => {
+                let report = self.report();
+                match args.first().map_or("text", |s| s.as_str()) {
+                    "text" => Ok(report.text()),
+                    "csv" => Ok(report.csv()),
+                    format => bail!("Unknown format '{}', expected text or csv", format),
                 }
             }
+            "fields" => {
+                let limit = args.first().map_or(Ok(10), |s| s.parse())?;
+                let assignments = self.field_matching().assignments(limit);
+                if assignments.is_empty() {
+                    bail!("No assignment of fields fits every valid ticket");
+                }
+
+                Ok(assignments
+                    .iter()
+                    .enumerate()
+                    .map(|(i, assignment)| {
+                        let fields = assignment
+                            .iter()
+                            .map(|(position, field_name)| format!("{}: {}", position, field_name))
+                            .join("\n");
+                        format!("Assignment {}:\n{}", i + 1, fields)
+                    })
+                    .join("\n\n"))
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
+    }
+}
+
+impl Day16Solver {
+    // The product of the values on my ticket of every field whose name starts with the prefix
+    fn product_of_fields(&self, prefix: &str) -> Result<u64> {
+        let fields = self
+            .field_matching()
+            .solve()
+            .unique()?
+            .into_iter()
+            .filter(|(_, field_name)| field_name.starts_with(prefix))
+            .collect_vec();
+        if fields.is_empty() {
+            bail!("No fields start with '{}'", prefix);
         }
 
-        let mut taken_field_names = HashSet::new();
-        let mut field_order = field_mapping
+        Ok(fields
             .iter()
-            .enumerate()
-            .sorted_by_key(|(_, s)| s.len())
-            .map(|(i, possible_field_names)| {
-                let field_name = possible_field_names
-                    .iter()
-                    .filter(|field_name| !taken_field_names.contains(field_name))
-                    .exactly_one()
-                    .map_err(|_| anyhow!("No single solution"))?;
-                taken_field_names.insert(field_name);
+            .map(|(i, _)| self.my_ticket.0[*i] as u64)
+            .product())
+    }
 
-                Ok((i, field_name))
-            })
-            .collect::<Result<Vec<_>>>()?;
-        field_order.sort_by_key(|(i, _)| *i);
+    fn report(&self) -> Report<'_> {
+        let tickets = self
+            .nearby_tickets
+            .iter()
+            .map(|ticket| (ticket, self.rules.validate_ticket(ticket).collect()))
+            .collect();
+        let my_ticket = self.field_matching().solve().unique().map(|fields| {
+            fields
+                .into_iter()
+                .map(|(i, name)| (name, self.my_ticket.0[i]))
+                .collect()
+        });
 
-        let sum: u64 = self
-            .my_ticket
-            .0
+        Report {
+            tickets,
+            candidates: self.candidates(),
+            my_ticket,
+        }
+    }
+
+    fn field_matching(&self) -> Matching<usize, &str> {
+        Matching::new(self.candidates())
+    }
+
+    // Every position on the tickets, with the fields its values on all valid tickets fit
+    fn candidates(&self) -> Vec<(usize, Vec<&str>)> {
+        let valid_tickets = self
+            .nearby_tickets
             .iter()
-            .enumerate()
-            .filter(|(i, _)| field_order[*i].1.starts_with("departure"))
-            .map(|(_, value)| *value as u64)
-            .product();
+            .filter(|ticket| self.rules.validate_ticket(ticket).next().is_none())
+            .collect_vec();
 
-        Ok(format!("Product of departure fields: {}", sum))
+        (0..self.my_ticket.0.len())
+            .map(|i| {
+                let candidates = self
+                    .rules
+                    .rules
+                    .iter()
+                    .filter(|rule| {
+                        valid_tickets.iter().all(|ticket| {
+                            ticket
+                                .0
+                                .get(i)
+                                .into_iter()
+                                .all(|value| rule.contains(*value))
+                        })
+                    })
+                    .map(|rule| rule.name.as_str())
+                    .sorted()
+                    .collect_vec();
+                (i, candidates)
+            })
+            .collect()
     }
 }
 
 fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
-    let rules = input
-        .lines()
-        .take(20)
-        .map(|line| {
-            let line = line?;
-            let key = line.chars().take_while(|c| *c != ':').collect::<String>();
-            let range1_string = line[(key.len() + 2)..]
-                .chars()
-                .take_while(|c| *c != ' ')
-                .collect::<String>();
-            let range1 = parse_range(&range1_string)?;
-            let range2 = parse_range(&line[(key.len() + range1_string.len() + 6)..])?;
-
-            Ok((key, (range1, range2)))
-        })
-        .collect::<Result<_>>()?;
-    let my_ticket = input
-        .lines()
-        .nth(2)
-        .ok_or(anyhow!("My ticket not found"))??
-        .parse()?;
-    let nearby_tickets = input
-        .lines()
-        .skip(2)
-        .map(|line| line?.parse())
+    let lines = input.lines().collect::<Result<Vec<_>, _>>()?;
+
+    // The rules come first, while the tickets are found by the headers of their sections
+    let mut sections = lines.split(|line| line.is_empty());
+    let rules = sections
+        .next()
+        .ok_or(anyhow!("No rules"))?
+        .iter()
+        .map(|line| line.parse())
         .collect::<Result<_>>()?;
+
+    let mut my_ticket = None;
+    let mut nearby_tickets = None;
+    for section in sections.filter(|section| !section.is_empty()) {
+        let tickets = section[1..]
+            .iter()
+            .map(|line| line.parse())
+            .collect::<Result<Vec<Ticket>>>()?;
+        match section[0].as_str() {
+            "your ticket:" => {
+                let ticket = tickets.into_iter().exactly_one();
+                my_ticket = Some(ticket.map_err(|_| anyhow!("Expected exactly one ticket"))?);
+            }
+            "nearby tickets:" => nearby_tickets = Some(tickets),
+            header => bail!("Unknown section '{}'", header),
+        }
+    }
+
     Ok(Box::new(Day16Solver {
         rules: TicketRules { rules },
-        my_ticket,
-        nearby_tickets,
+        my_ticket: my_ticket.ok_or(anyhow!("My ticket not found"))?,
+        nearby_tickets: nearby_tickets.ok_or(anyhow!("Nearby tickets not found"))?,
     }))
 }
-
-fn parse_range(s: &str) -> Result<RangeInclusive<u32>> {
-    let low_string = s.chars().take_while(|c| *c != '-').collect::<String>();
-    let low = low_string.parse()?;
-    let high = s[(low_string.len() + 1)..].parse()?;
-
-    Ok(low..=high)
-}
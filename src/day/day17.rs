@@ -1,133 +1,318 @@
+use crate::animation::Animation;
+use crate::automaton::LifeRule;
 use crate::day::{Day, DynSolver, Solver};
+use crate::grid::{Cell, Grid};
 
-use std::collections::HashSet;
-use std::hash::Hash;
+use std::collections::{HashMap, HashSet};
 use std::io::BufRead;
-use std::mem;
+use std::iter;
+use std::time::Instant;
 
-use anyhow::Result;
-use itertools::iproduct;
+use anyhow::{anyhow, bail, Result};
+use itertools::Itertools;
+
+const CYCLES: usize = 6;
 
 pub const DAY17: Day = Day {
     title: "Conway Cubes",
     solver_from_input,
 };
 
-trait Coords: Sized {
-    fn neighbors(&self) -> Vec<Self>;
+#[derive(Clone, Copy, PartialEq)]
+enum Cube {
+    Active,
+    Inactive,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-struct Coords3(isize, isize, isize);
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-struct Coords4(isize, isize, isize, isize);
-
-impl Coords for Coords3 {
-    fn neighbors(&self) -> Vec<Self> {
-        let Self(x, y, z) = self;
-        iproduct!((x - 1)..=(x + 1), (y - 1)..=(y + 1), (z - 1)..=(z + 1))
-            .map(|(x, y, z)| Self(x, y, z))
-            .filter(|c| c != self)
-            .collect()
+impl Cell for Cube {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '#' => Some(Self::Active),
+            '.' => Some(Self::Inactive),
+            _ => None,
+        }
     }
-}
 
-impl Coords for Coords4 {
-    fn neighbors(&self) -> Vec<Self> {
-        let Self(x, y, z, w) = self;
-        iproduct!(
-            (x - 1)..=(x + 1),
-            (y - 1)..=(y + 1),
-            (z - 1)..=(z + 1),
-            (w - 1)..=(w + 1)
-        )
-        .map(|(x, y, z, w)| Self(x, y, z, w))
-        .filter(|c| c != self)
-        .collect()
+    fn to_char(&self) -> char {
+        match self {
+            Self::Active => '#',
+            Self::Inactive => '.',
+        }
     }
 }
 
-struct PocketUniverse<C> {
-    active_cubes: HashSet<C>,
+type Coords = Vec<isize>;
+
+struct PocketUniverse {
+    // Offsets to every neighbor, in all dimensions
+    offsets: Vec<Coords>,
+    rule: LifeRule,
+    active_cubes: HashSet<Coords>,
 }
 
-impl<C> PocketUniverse<C>
-where
-    C: Coords,
-    C: Copy + Eq + Hash,
-{
-    fn step(&mut self) {
-        let mut new_cubes = HashSet::new();
+impl PocketUniverse {
+    fn new(plane: &HashSet<(isize, isize)>, dimensions: usize, rule: LifeRule) -> Result<Self> {
+        if dimensions < 2 {
+            bail!("Unsupported dimensions: {}", dimensions);
+        }
+        if rule.births_from_nothing() {
+            bail!(
+                "Rules with birth on 0 neighbors would activate every cube in the pocket dimension"
+            );
+        }
+
+        let offsets = (0..dimensions)
+            .map(|_| -1..=1)
+            .multi_cartesian_product()
+            .filter(|offset| offset.iter().any(|d| *d != 0))
+            .collect();
+        let active_cubes = plane
+            .iter()
+            .map(|(x, y)| {
+                let mut coords = vec![0; dimensions];
+                coords[0] = *x;
+                coords[1] = *y;
+                coords
+            })
+            .collect();
 
+        Ok(Self {
+            offsets,
+            rule,
+            active_cubes,
+        })
+    }
+
+    // Every state is symmetric under mirroring any of the higher dimensions, as the initial
+    // state lies in the x/y plane, so only cubes with non-negative higher coordinates are stored
+    fn canonical(coords: &[isize]) -> Coords {
+        coords
+            .iter()
+            .enumerate()
+            .map(|(i, c)| if i < 2 { *c } else { c.abs() })
+            .collect()
+    }
+
+    // The number of mirror images of a stored cube
+    fn mirrors(coords: &[isize]) -> usize {
+        1 << coords[2..].iter().filter(|c| **c != 0).count()
+    }
+
+    fn step(&mut self) {
+        // Every neighbor of a stored cube is counted once for each of the cube's mirror images,
+        // and then divided by its own mirror images, which works out to its actual neighbor count
+        let mut neighbor_counts = HashMap::new();
+        for coords in &self.active_cubes {
+            // Active cubes without active neighbors must still be checked for survival
+            neighbor_counts.entry(coords.clone()).or_insert(0);
+        }
         for coords in &self.active_cubes {
-            self.check(*coords, &mut new_cubes, true);
+            let mirrors = Self::mirrors(coords);
+            for neighbor in neighbors(coords, &self.offsets) {
+                *neighbor_counts
+                    .entry(Self::canonical(&neighbor))
+                    .or_insert(0) += mirrors;
+            }
+        }
+
+        let active_cubes = &self.active_cubes;
+        let rule = &self.rule;
+        self.active_cubes = neighbor_counts
+            .into_iter()
+            .filter(|(coords, count)| {
+                let active_neighbors = count / Self::mirrors(coords);
+                rule.next(active_cubes.contains(coords), active_neighbors)
+            })
+            .map(|(coords, _)| coords)
+            .collect();
+    }
+
+    fn active_cubes(&self) -> usize {
+        self.active_cubes
+            .iter()
+            .map(|coords| Self::mirrors(coords))
+            .sum()
+    }
+
+    fn run(&mut self, cycles: usize) -> usize {
+        for _ in 0..cycles {
+            self.step();
         }
-        mem::swap(&mut new_cubes, &mut self.active_cubes);
+        self.active_cubes()
     }
 
-    fn check(&self, coords: C, new_cubes: &mut HashSet<C>, check_neighbors: bool) {
-        let neighbors = coords.neighbors();
-        let active_neighbors = neighbors
+    // The x/y plane at the given higher dimension coordinates, spanning every active cube
+    fn slice(&self, rest: &[isize]) -> Grid<Cube> {
+        let (xs, ys): (Vec<_>, Vec<_>) = self
+            .active_cubes
+            .iter()
+            .map(|coords| (coords[0], coords[1]))
+            .unzip();
+        let (min_x, max_x) = xs.into_iter().minmax().into_option().unwrap_or((0, -1));
+        let (min_y, max_y) = ys.into_iter().minmax().into_option().unwrap_or((0, -1));
+
+        let rest = rest.iter().map(|c| c.abs()).collect_vec();
+        let plane = self
+            .active_cubes
             .iter()
-            .filter(|coords| self.active_cubes.contains(coords))
-            .count();
-
-        let is_active = self.active_cubes.contains(&coords);
-        if is_active && (active_neighbors == 2 || active_neighbors == 3) {
-            new_cubes.insert(coords);
-        } else if !is_active && active_neighbors == 3 {
-            new_cubes.insert(coords);
+            .filter(|coords| coords[2..] == rest[..])
+            .map(|coords| (coords[0], coords[1]))
+            .collect::<HashSet<_>>();
+        Grid::from_fn(
+            (max_x - min_x + 1) as usize,
+            (max_y - min_y + 1) as usize,
+            |(x, y)| {
+                if plane.contains(&(x as isize + min_x, y as isize + min_y)) {
+                    Cube::Active
+                } else {
+                    Cube::Inactive
+                }
+            },
+        )
+    }
+
+    fn animate(&mut self, rest: &[isize], animation: &mut Animation) -> Result<()> {
+        for cycle in 0..=CYCLES {
+            if cycle > 0 {
+                self.step();
+            }
+            animation.frame(
+                &format!("Cycle {}, slice {}", cycle, rest.iter().join(",")),
+                &self.slice(rest),
+            )?;
         }
 
-        if check_neighbors {
-            for neighbor_coords in neighbors {
-                self.check(neighbor_coords, new_cubes, false);
+        Ok(())
+    }
+}
+
+fn neighbors<'a>(coords: &'a [isize], offsets: &'a [Coords]) -> impl Iterator<Item = Coords> + 'a {
+    offsets
+        .iter()
+        .map(move |offset| coords.iter().zip(offset).map(|(c, d)| c + d).collect())
+}
+
+// The original step, checking every neighbor of every active cube without exploiting symmetry,
+// kept as a reference for benchmarking
+fn step_naive(
+    active_cubes: &HashSet<Coords>,
+    offsets: &[Coords],
+    rule: &LifeRule,
+) -> HashSet<Coords> {
+    let mut new_cubes = HashSet::new();
+    for coords in active_cubes {
+        for coords in iter::once(coords.clone()).chain(neighbors(coords, offsets)) {
+            let active_neighbors = neighbors(&coords, offsets)
+                .filter(|coords| active_cubes.contains(coords))
+                .count();
+            if rule.next(active_cubes.contains(&coords), active_neighbors) {
+                new_cubes.insert(coords);
             }
         }
     }
+    new_cubes
 }
 
 struct Day17Solver(HashSet<(isize, isize)>);
 impl Solver for Day17Solver {
     fn part1(&self) -> Result<String> {
-        let mut pocket_universe = PocketUniverse {
-            active_cubes: self.0.iter().map(|(x, y)| Coords3(*x, *y, 0)).collect(),
-        };
-        for _ in 0..6 {
-            pocket_universe.step();
-        }
-
-        let active_cubes = pocket_universe.active_cubes.len();
+        let active_cubes = PocketUniverse::new(&self.0, 3, LifeRule::conway())?.run(CYCLES);
 
         Ok(format!("Number of active cubes: {}", active_cubes))
     }
 
     fn part2(&self) -> Result<String> {
-        let mut pocket_universe = PocketUniverse {
-            active_cubes: self.0.iter().map(|(x, y)| Coords4(*x, *y, 0, 0)).collect(),
-        };
-        for _ in 0..6 {
-            pocket_universe.step();
-        }
-
-        let active_cubes = pocket_universe.active_cubes.len();
+        let active_cubes = PocketUniverse::new(&self.0, 4, LifeRule::conway())?.run(CYCLES);
 
         Ok(format!("Number of active cubes: {}", active_cubes))
     }
+
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "run" => {
+                let dimensions = args
+                    .first()
+                    .ok_or(anyhow!("Missing dimensions"))?
+                    .parse::<usize>()?;
+                let rule = args.get(1).map_or(Ok(LifeRule::conway()), |s| s.parse())?;
+                let cycles = args.get(2).map_or(Ok(CYCLES), |s| s.parse())?;
+
+                let active_cubes = PocketUniverse::new(&self.0, dimensions, rule)?.run(cycles);
+
+                Ok(format!(
+                    "Number of active cubes after {} cycles in {} dimensions: {}",
+                    cycles, dimensions, active_cubes
+                ))
+            }
+            "bench" => {
+                let dimensions = args
+                    .first()
+                    .ok_or(anyhow!("Missing dimensions"))?
+                    .parse::<usize>()?;
+                let rule = args.get(1).map_or(Ok(LifeRule::conway()), |s| s.parse())?;
+                let cycles = args.get(2).map_or(Ok(CYCLES), |s| s.parse())?;
+
+                let start = Instant::now();
+                let mut pocket_universe = PocketUniverse::new(&self.0, dimensions, rule)?;
+                let mut active_cubes = pocket_universe.active_cubes.clone();
+                for _ in 0..cycles {
+                    active_cubes = step_naive(
+                        &active_cubes,
+                        &pocket_universe.offsets,
+                        &pocket_universe.rule,
+                    );
+                }
+                let naive_time = start.elapsed();
+
+                let start = Instant::now();
+                let fast = pocket_universe.run(cycles);
+                let fast_time = start.elapsed();
+
+                if fast != active_cubes.len() {
+                    bail!(
+                        "Results differ: {} active cubes, expected {}",
+                        fast,
+                        active_cubes.len()
+                    );
+                }
+
+                Ok(format!(
+                    "{} active cubes after {} cycles in {} dimensions\nNaive: {:?}\nFast: {:?} ({:.1}x)",
+                    fast,
+                    cycles,
+                    dimensions,
+                    naive_time,
+                    fast_time,
+                    naive_time.as_secs_f64() / fast_time.as_secs_f64()
+                ))
+            }
+            "animate" => {
+                let (mut animation, args) = Animation::from_args(args)?;
+                let dimensions = args
+                    .first()
+                    .ok_or(anyhow!("Missing dimensions"))?
+                    .parse::<usize>()?;
+                let mut rest = args[1..]
+                    .iter()
+                    .map(|n| Ok(n.parse()?))
+                    .collect::<Result<Vec<_>>>()?;
+                rest.resize(dimensions.max(2) - 2, 0);
+
+                PocketUniverse::new(&self.0, dimensions, LifeRule::conway())?
+                    .animate(&rest, &mut animation)?;
+
+                Ok(animation.finish())
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
+    }
 }
 
 fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
-    let lines = input.lines();
-    let active_cubes = lines
-        .enumerate()
-        .filter_map(|(y, line)| Some((y, line.ok()?)))
-        .flat_map(|(y, line)| {
-            line.chars()
-                .enumerate()
-                .filter(|(_, c)| *c == '#')
-                .map(move |(x, _)| (x as isize, y as isize))
-                .collect::<Vec<_>>()
-        })
+    let active_cubes = Grid::<Cube>::parse(input)?
+        .cells()
+        .filter(|(_, cube)| **cube == Cube::Active)
+        .map(|((x, y), _)| (x as isize, y as isize))
         .collect();
     Ok(Box::new(Day17Solver(active_cubes)))
 }
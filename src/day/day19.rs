@@ -1,25 +1,21 @@
 use crate::day::{Day, DynSolver, Solver};
 
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::io::BufRead;
 use std::str::FromStr;
 
-use anyhow::{anyhow, Result};
-use std::iter;
+use anyhow::{anyhow, bail, Result};
+use itertools::Itertools;
 
 pub const DAY19: Day = Day {
     title: "Monster Messages",
     solver_from_input,
 };
 
-#[derive(Clone, Copy, PartialEq)]
-enum Character {
-    A,
-    B,
-}
-
 #[derive(Clone)]
 enum Rule {
-    Literal(Character),
+    Literal(String),
     // Match one list out of the supplied lists of rules
     OneOf(Vec<Vec<usize>>),
 }
@@ -28,76 +24,625 @@ impl FromStr for Rule {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self> {
-        match s {
-            "\"a\"" => Ok(Rule::Literal(Character::A)),
-            "\"b\"" => Ok(Rule::Literal(Character::B)),
-            _ => {
-                let lists = s
-                    .split(" | ")
-                    .map(|list_part| list_part.split(' ').map(|n| Ok(n.parse()?)).collect())
-                    .collect::<Result<_>>()?;
-
-                Ok(Rule::OneOf(lists))
-            }
-        }
-    }
-}
-
-struct Message(Vec<Character>);
-
-impl Message {
-    fn matches(&self, rule: &Rule, rules: &[Rule]) -> bool {
-        Self::matches_impl(&self.0, rule, rules)
-            .filter(|m| m.is_empty())
-            .next()
-            .is_some()
-    }
-
-    // TODO reduce allocations
-    fn matches_impl<'a>(
-        message: &'a [Character],
-        rule: &'a Rule,
-        rules: &'a [Rule],
-    ) -> Box<dyn Iterator<Item = &'a [Character]> + 'a> {
-        match rule {
-            Rule::OneOf(lists) => Box::new(lists.iter().flat_map(move |list| {
-                let mut messages: Box<dyn Iterator<Item = &'a [Character]> + 'a> =
-                    Box::new(iter::once(message));
-                for index in list {
-                    let rule = &rules[*index];
-                    messages = Box::new(
-                        messages.flat_map(move |message| Self::matches_impl(message, rule, rules)),
-                    );
+        if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+            return Ok(Rule::Literal(s[1..(s.len() - 1)].to_string()));
+        }
+
+        let lists = s
+            .split(" | ")
+            .map(|list_part| list_part.split(' ').map(|n| Ok(n.parse()?)).collect())
+            .collect::<Result<_>>()?;
+
+        Ok(Rule::OneOf(lists))
+    }
+}
+
+struct Message(Vec<char>);
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in &self.0 {
+            write!(f, "{}", c)?;
+        }
+
+        Ok(())
+    }
+}
+
+enum ParseTree {
+    Literal(usize, String),
+    Rule(usize, Vec<ParseTree>),
+}
+
+impl fmt::Display for ParseTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseTree::Literal(index, literal) => write!(f, "{}:\"{}\"", index, literal),
+            ParseTree::Rule(index, children) => {
+                write!(f, "{}(", index)?;
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", child)?;
                 }
-                messages
-            })),
-            Rule::Literal(c) => {
-                if let Some(first) = message.first() {
-                    if first == c {
-                        Box::new(iter::once(&message[1..]))
-                    } else {
-                        Box::new(iter::empty())
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+// An Earley item: alternative `alternative` of rule `rule`, matched up to `dot`, started at `origin`
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Item {
+    rule: usize,
+    alternative: usize,
+    dot: usize,
+    origin: usize,
+}
+
+struct Grammar {
+    rules: Vec<Option<Rule>>,
+    nullable: Vec<bool>,
+}
+
+impl Grammar {
+    fn new(rules: Vec<Option<Rule>>) -> Self {
+        let mut nullable = vec![false; rules.len()];
+        loop {
+            let mut changed = false;
+            for (index, rule) in rules.iter().enumerate() {
+                if nullable[index] {
+                    continue;
+                }
+
+                let is_nullable = match rule {
+                    Some(Rule::Literal(literal)) => literal.is_empty(),
+                    Some(Rule::OneOf(lists)) => lists.iter().any(|list| {
+                        list.iter()
+                            .all(|index| nullable.get(*index).copied().unwrap_or(false))
+                    }),
+                    None => false,
+                };
+                if is_nullable {
+                    nullable[index] = true;
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        Self { rules, nullable }
+    }
+
+    fn is_nullable(&self, rule: usize) -> bool {
+        self.nullable.get(rule).copied().unwrap_or(false)
+    }
+
+    fn rule(&self, index: usize) -> Option<&Rule> {
+        self.rules.get(index).and_then(Option::as_ref)
+    }
+
+    fn alternatives(&self, rule: usize) -> &[Vec<usize>] {
+        match self.rule(rule) {
+            Some(Rule::OneOf(lists)) => lists,
+            _ => &[],
+        }
+    }
+
+    fn next_symbol(&self, item: &Item) -> Option<usize> {
+        self.alternatives(item.rule)[item.alternative]
+            .get(item.dot)
+            .copied()
+    }
+
+    fn matches(&self, start: usize, message: &Message) -> bool {
+        match self.rule(start) {
+            Some(Rule::Literal(literal)) => message.0.iter().copied().eq(literal.chars()),
+            Some(Rule::OneOf(_)) => self.chart(start, message).0,
+            None => false,
+        }
+    }
+
+    fn parse(&self, start: usize, message: &Message) -> Option<ParseTree> {
+        let (matched, completed) = match self.rule(start)? {
+            Rule::Literal(_) => (true, HashSet::new()),
+            Rule::OneOf(_) => self.chart(start, message),
+        };
+        if !matched {
+            return None;
+        }
+
+        let mut extractor = TreeExtractor {
+            grammar: self,
+            message: &message.0,
+            completed,
+            visiting: HashSet::new(),
+        };
+        extractor.build(start, 0, message.0.len())
+    }
+
+    // Runs the Earley recognizer, returning whether the message matched as well as every completed
+    // (rule, start, end) span, which is used to recover parse trees
+    fn chart(&self, start: usize, message: &Message) -> (bool, HashSet<(usize, usize, usize)>) {
+        let message = &message.0;
+        let mut sets = vec![Vec::new(); message.len() + 1];
+        let mut seen = vec![HashSet::new(); message.len() + 1];
+        let mut completed = HashSet::new();
+
+        for alternative in 0..self.alternatives(start).len() {
+            let item = Item {
+                rule: start,
+                alternative,
+                dot: 0,
+                origin: 0,
+            };
+            seen[0].insert(item);
+            sets[0].push(item);
+        }
+
+        for position in 0..sets.len() {
+            let mut i = 0;
+            while i < sets[position].len() {
+                let item = sets[position][i];
+                i += 1;
+
+                let mut added = Vec::new();
+                match self.next_symbol(&item) {
+                    Some(symbol) => match self.rule(symbol) {
+                        Some(Rule::Literal(literal)) => {
+                            if let Some(end) = match_literal(literal, message, position) {
+                                let advanced = Item {
+                                    dot: item.dot + 1,
+                                    ..item
+                                };
+                                if end == position {
+                                    added.push(advanced);
+                                } else if seen[end].insert(advanced) {
+                                    sets[end].push(advanced);
+                                }
+                            }
+                        }
+                        Some(Rule::OneOf(lists)) => {
+                            for alternative in 0..lists.len() {
+                                added.push(Item {
+                                    rule: symbol,
+                                    alternative,
+                                    dot: 0,
+                                    origin: position,
+                                });
+                            }
+                            if self.is_nullable(symbol) {
+                                added.push(Item {
+                                    dot: item.dot + 1,
+                                    ..item
+                                });
+                            }
+                        }
+                        _ => {}
+                    },
+                    None => {
+                        completed.insert((item.rule, item.origin, position));
+                        for waiting in &sets[item.origin] {
+                            if self.next_symbol(waiting) == Some(item.rule) {
+                                added.push(Item {
+                                    dot: waiting.dot + 1,
+                                    ..*waiting
+                                });
+                            }
+                        }
+                    }
+                }
+
+                for item in added {
+                    if seen[position].insert(item) {
+                        sets[position].push(item);
+                    }
+                }
+            }
+        }
+
+        let matched = completed.contains(&(start, 0, message.len()));
+        (matched, completed)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Recursion {
+    Left,
+    Right,
+    Center,
+}
+
+impl fmt::Display for Recursion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Recursion::Left => write!(f, "left"),
+            Recursion::Right => write!(f, "right"),
+            Recursion::Center => write!(f, "center"),
+        }
+    }
+}
+
+// Shortest and longest match of a rule, where a missing maximum means the rule is unbounded
+type Lengths = (usize, Option<usize>);
+
+impl Grammar {
+    fn references(&self, rule: usize) -> impl Iterator<Item = usize> + '_ {
+        self.alternatives(rule).iter().flatten().copied()
+    }
+
+    fn defined_rules(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.rules.len()).filter(move |index| self.rule(*index).is_some())
+    }
+
+    fn undefined_references(&self) -> Vec<(usize, usize)> {
+        self.defined_rules()
+            .flat_map(|rule| {
+                self.references(rule)
+                    .filter(|reference| self.rule(*reference).is_none())
+                    .map(move |reference| (rule, reference))
+            })
+            .unique()
+            .collect()
+    }
+
+    fn reachable(&self, start: usize) -> HashSet<usize> {
+        let mut reachable = HashSet::new();
+        let mut stack = vec![start];
+        while let Some(rule) = stack.pop() {
+            if self.rule(rule).is_some() && reachable.insert(rule) {
+                stack.extend(self.references(rule));
+            }
+        }
+
+        reachable
+    }
+
+    fn unreachable(&self, start: usize) -> Vec<usize> {
+        let reachable = self.reachable(start);
+        self.defined_rules()
+            .filter(|rule| !reachable.contains(rule))
+            .collect()
+    }
+
+    // Follows every derivation from the rule back to itself, tracking whether something that cannot
+    // match the empty string was left behind on either side of the recursive reference
+    fn recursion(&self, rule: usize) -> Vec<Recursion> {
+        let mut kinds = HashSet::new();
+        let mut visited = HashSet::new();
+        let mut stack = vec![(rule, false, false)];
+        while let Some((current, left, right)) = stack.pop() {
+            for list in self.alternatives(current) {
+                for (i, symbol) in list.iter().enumerate() {
+                    let is_material = |symbol: &usize| !self.is_nullable(*symbol);
+                    let left = left || list[..i].iter().any(is_material);
+                    let right = right || list[(i + 1)..].iter().any(is_material);
+
+                    if *symbol == rule {
+                        if !left {
+                            kinds.insert(Recursion::Left);
+                        }
+                        if !right {
+                            kinds.insert(Recursion::Right);
+                        }
+                        if left && right {
+                            kinds.insert(Recursion::Center);
+                        }
+                    } else if visited.insert((*symbol, left, right)) {
+                        stack.push((*symbol, left, right));
                     }
+                }
+            }
+        }
+
+        kinds.into_iter().sorted().collect()
+    }
+
+    fn min_lengths(&self) -> Vec<Option<usize>> {
+        let mut min_lengths = self
+            .rules
+            .iter()
+            .map(|rule| match rule {
+                Some(Rule::Literal(literal)) => Some(literal.chars().count()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        loop {
+            let mut changed = false;
+            for rule in self.defined_rules() {
+                let min_length = self
+                    .alternatives(rule)
+                    .iter()
+                    .filter_map(|list| {
+                        list.iter()
+                            .map(|symbol| min_lengths.get(*symbol).copied().flatten())
+                            .sum::<Option<usize>>()
+                    })
+                    .min();
+                if min_length.is_some() && min_length != min_lengths[rule] {
+                    min_lengths[rule] = min_length;
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break min_lengths;
+            }
+        }
+    }
+
+    // Lengths for every rule, or `None` for rules that can never match
+    fn lengths(&self) -> Vec<Option<Lengths>> {
+        let min_lengths = self.min_lengths();
+        let productive = |symbol: &usize| min_lengths.get(*symbol).copied().flatten().is_some();
+        let productive_references = |rule: usize| {
+            self.alternatives(rule)
+                .iter()
+                .filter(|list| list.iter().all(productive))
+                .flatten()
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        let recursive = (0..self.rules.len())
+            .map(|rule| {
+                let mut visited = HashSet::new();
+                let mut stack = productive_references(rule);
+                while let Some(current) = stack.pop() {
+                    if current == rule {
+                        return true;
+                    }
+                    if visited.insert(current) {
+                        stack.extend(productive_references(current));
+                    }
+                }
+
+                false
+            })
+            .collect::<Vec<_>>();
+
+        fn max_length(
+            rule: usize,
+            grammar: &Grammar,
+            min_lengths: &[Option<usize>],
+            recursive: &[bool],
+            counted: &mut HashMap<usize, Option<usize>>,
+        ) -> Option<usize> {
+            if let Some(max_length) = counted.get(&rule) {
+                return *max_length;
+            }
+
+            let max_length = if recursive[rule] {
+                None
+            } else {
+                match grammar.rule(rule) {
+                    Some(Rule::Literal(literal)) => Some(literal.chars().count()),
+                    _ => grammar
+                        .alternatives(rule)
+                        .iter()
+                        .filter(|list| {
+                            list.iter()
+                                .all(|symbol| min_lengths.get(*symbol).copied().flatten().is_some())
+                        })
+                        .map(|list| {
+                            list.iter()
+                                .map(|symbol| {
+                                    max_length(*symbol, grammar, min_lengths, recursive, counted)
+                                })
+                                .sum::<Option<usize>>()
+                        })
+                        .try_fold(0, |a, b| Some(a.max(b?))),
+                }
+            };
+            counted.insert(rule, max_length);
+
+            max_length
+        }
+
+        let mut counted = HashMap::new();
+        (0..self.rules.len())
+            .map(|rule| {
+                let min_length = min_lengths[rule]?;
+                let max_length = max_length(rule, self, &min_lengths, &recursive, &mut counted);
+                Some((min_length, max_length))
+            })
+            .collect()
+    }
+
+    fn regex(&self, rule: usize) -> Result<String> {
+        fn build(
+            rule: usize,
+            grammar: &Grammar,
+            built: &mut HashMap<usize, String>,
+        ) -> Result<String> {
+            if let Some(regex) = built.get(&rule) {
+                return Ok(regex.clone());
+            }
+
+            let regex = match grammar.rule(rule) {
+                Some(Rule::Literal(literal)) => escape_regex(literal),
+                Some(Rule::OneOf(lists)) => {
+                    let alternatives = lists
+                        .iter()
+                        .map(|list| {
+                            list.iter()
+                                .map(|symbol| build(*symbol, grammar, built))
+                                .collect::<Result<String>>()
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    match alternatives.len() {
+                        0 => bail!("Rule {} has no alternatives", rule),
+                        1 => alternatives.into_iter().next().unwrap(),
+                        _ => format!("(?:{})", alternatives.join("|")),
+                    }
+                }
+                None => bail!("Rule {} is not defined", rule),
+            };
+            built.insert(rule, regex.clone());
+
+            Ok(regex)
+        }
+
+        if let Some(recursive) = self
+            .reachable(rule)
+            .into_iter()
+            .sorted()
+            .find(|reachable| !self.recursion(*reachable).is_empty())
+        {
+            bail!("Rule {} is recursive through rule {}", rule, recursive);
+        }
+
+        Ok(format!("^{}$", build(rule, self, &mut HashMap::new())?))
+    }
+
+    fn analysis(&self) -> String {
+        let lengths = self.lengths();
+        let mut report = self
+            .defined_rules()
+            .map(|rule| {
+                let lengths = match lengths[rule] {
+                    Some((min, Some(max))) if min == max => format!("length {}", min),
+                    Some((min, Some(max))) => format!("length {}-{}", min, max),
+                    Some((min, None)) => format!("length {} or more", min),
+                    None => "never matches".to_string(),
+                };
+                let recursion = self.recursion(rule);
+                if recursion.is_empty() {
+                    format!("{}: {}", rule, lengths)
                 } else {
-                    Box::new(iter::empty())
+                    format!(
+                        "{}: {}, {}-recursive",
+                        rule,
+                        lengths,
+                        recursion.iter().join("/")
+                    )
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let undefined = self.undefined_references();
+        if !undefined.is_empty() {
+            report.push(format!(
+                "Undefined references: {}",
+                undefined
+                    .iter()
+                    .map(|(rule, reference)| format!("{} -> {}", rule, reference))
+                    .join(", ")
+            ));
+        }
+        let unreachable = self.unreachable(0);
+        if !unreachable.is_empty() {
+            report.push(format!(
+                "Rules unreachable from rule 0: {}",
+                unreachable.iter().join(", ")
+            ));
+        }
+
+        report.join("\n")
+    }
+}
+
+struct TreeExtractor<'a> {
+    grammar: &'a Grammar,
+    message: &'a [char],
+    completed: HashSet<(usize, usize, usize)>,
+    visiting: HashSet<(usize, usize, usize)>,
+}
+
+impl TreeExtractor<'_> {
+    fn spans(&self, rule: usize, start: usize, end: usize) -> bool {
+        match self.grammar.rule(rule) {
+            Some(Rule::Literal(literal)) => {
+                match_literal(literal, self.message, start) == Some(end)
+            }
+            Some(Rule::OneOf(_)) => self.completed.contains(&(rule, start, end)),
+            None => false,
+        }
+    }
+
+    fn build(&mut self, rule: usize, start: usize, end: usize) -> Option<ParseTree> {
+        if !self.spans(rule, start, end) {
+            return None;
+        }
+
+        let alternatives = match self.grammar.rule(rule)? {
+            Rule::Literal(literal) => return Some(ParseTree::Literal(rule, literal.clone())),
+            Rule::OneOf(lists) => lists,
+        };
+
+        // Guard against cyclic derivations such as `A: A` spanning the same input
+        if !self.visiting.insert((rule, start, end)) {
+            return None;
+        }
+        let tree = alternatives.iter().find_map(|list| {
+            self.build_sequence(list, start, end)
+                .map(|children| ParseTree::Rule(rule, children))
+        });
+        self.visiting.remove(&(rule, start, end));
+
+        tree
+    }
+
+    fn build_sequence(
+        &mut self,
+        symbols: &[usize],
+        start: usize,
+        end: usize,
+    ) -> Option<Vec<ParseTree>> {
+        let (first, rest) = match symbols.split_first() {
+            Some(split) => split,
+            None => return if start == end { Some(Vec::new()) } else { None },
+        };
+
+        for middle in start..=end {
+            if !self.spans(*first, start, middle) {
+                continue;
+            }
+
+            if let Some(tree) = self.build(*first, start, middle) {
+                if let Some(mut children) = self.build_sequence(rest, middle, end) {
+                    children.insert(0, tree);
+                    return Some(children);
                 }
             }
         }
+
+        None
     }
 }
 
 struct Day19Solver {
-    rules: Vec<Rule>,
+    rules: Vec<Option<Rule>>,
     messages: Vec<Message>,
 }
+
+impl Day19Solver {
+    fn grammar(&self, looping: bool) -> Grammar {
+        let mut rules = self.rules.clone();
+        if looping {
+            rules.resize_with(rules.len().max(12), || None);
+            rules[8] = Some(Rule::OneOf(vec![vec![42, 8], vec![42]]));
+            rules[11] = Some(Rule::OneOf(vec![vec![42, 31], vec![42, 11, 31]]));
+        }
+
+        Grammar::new(rules)
+    }
+
+    fn count_matches(&self, grammar: &Grammar) -> usize {
+        self.messages
+            .iter()
+            .filter(|message| grammar.matches(0, message))
+            .count()
+    }
+}
+
 impl Solver for Day19Solver {
     fn part1(&self) -> Result<String> {
-        let rule0 = &self.rules[0];
-        let match_count = self
-            .messages
-            .iter()
-            .filter(|message| message.matches(rule0, &self.rules))
-            .count();
+        let match_count = self.count_matches(&self.grammar(false));
 
         Ok(format!(
             "Amount of messages matching rule 0: {}",
@@ -106,22 +651,75 @@ impl Solver for Day19Solver {
     }
 
     fn part2(&self) -> Result<String> {
-        let mut rules = self.rules.clone();
-        rules[8] = Rule::OneOf(vec![vec![42, 8], vec![42]]);
-        rules[11] = Rule::OneOf(vec![vec![42, 31], vec![42, 11, 31]]);
-
-        let rule0 = &rules[0];
-        let match_count = self
-            .messages
-            .iter()
-            .filter(|message| message.matches(rule0, &rules))
-            .count();
+        let match_count = self.count_matches(&self.grammar(true));
 
         Ok(format!(
             "Amount of messages matching rule 0: {}",
             match_count
         ))
     }
+
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "trees" => {
+                let grammar = self.grammar(parse_part(args.first())?);
+
+                Ok(self
+                    .messages
+                    .iter()
+                    .filter_map(|message| {
+                        let tree = grammar.parse(0, message)?;
+                        Some(format!("{}\n  {}", message, tree))
+                    })
+                    .join("\n"))
+            }
+            "analysis" => Ok(self.grammar(parse_part(args.first())?).analysis()),
+            "regex" => {
+                let rule = args
+                    .first()
+                    .ok_or(anyhow!("Missing rule argument"))?
+                    .parse()?;
+                self.grammar(parse_part(args.get(1))?).regex(rule)
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
+    }
+}
+
+// The end of the literal if it occurs in the message at the given position
+fn match_literal(literal: &str, message: &[char], position: usize) -> Option<usize> {
+    let end = position + literal.chars().count();
+    if message
+        .get(position..end)?
+        .iter()
+        .copied()
+        .eq(literal.chars())
+    {
+        Some(end)
+    } else {
+        None
+    }
+}
+
+fn escape_regex(literal: &str) -> String {
+    let mut escaped = String::new();
+    for c in literal.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+// Whether to use the looping rules of part 2
+fn parse_part(part: Option<&String>) -> Result<bool> {
+    match part.map(|s| s.as_str()) {
+        None | Some("1") => Ok(false),
+        Some("2") => Ok(true),
+        Some(part) => bail!("Invalid part '{}'", part),
+    }
 }
 
 fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
@@ -129,7 +727,7 @@ fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
         let mut rules = Vec::new();
         for line in input.lines() {
             let line = line?;
-            if line == "" {
+            if line.is_empty() {
                 break;
             }
 
@@ -137,8 +735,8 @@ fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
             let index = index_string.parse::<usize>()?;
             let rule = line[(index_string.len() + 2)..].parse::<Rule>()?;
 
-            rules.resize_with(rules.len().max(index + 1), || Rule::OneOf(vec![]));
-            rules[index] = rule;
+            rules.resize_with(rules.len().max(index + 1), || None);
+            rules[index] = Some(rule);
         }
 
         rules
@@ -146,19 +744,7 @@ fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
 
     let messages = input
         .lines()
-        .map(|line| {
-            let line = line?;
-            let m = line
-                .chars()
-                .map(|c| match c {
-                    'a' => Ok(Character::A),
-                    'b' => Ok(Character::B),
-                    _ => Err(anyhow!("Invalid character '{}'", c)),
-                })
-                .collect::<Result<_>>()?;
-
-            Ok(Message(m))
-        })
+        .map(|line| Ok(Message(line?.chars().collect())))
         .collect::<Result<_>>()?;
     Ok(Box::new(Day19Solver { rules, messages }))
 }
@@ -1,8 +1,11 @@
 use crate::day::{Day, DynSolver, Solver};
 
+use std::fmt;
 use std::io::BufRead;
+use std::str::FromStr;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+use itertools::Itertools;
 
 pub const DAY2: Day = Day {
     title: "Password Philosophy",
@@ -20,85 +23,183 @@ struct Password {
     password: String,
 }
 
+impl FromStr for Password {
+    type Err = anyhow::Error;
+
+    // e.g. "1-3 a: abcde"
+    fn from_str(s: &str) -> Result<Self> {
+        let (policy, password) = s
+            .split(": ")
+            .collect_tuple()
+            .ok_or(anyhow!("Invalid line '{}'", s))?;
+        let (range, character) = policy
+            .split(' ')
+            .collect_tuple()
+            .ok_or(anyhow!("Invalid policy '{}'", policy))?;
+        let (least, most) = range
+            .split('-')
+            .collect_tuple()
+            .ok_or(anyhow!("Invalid range '{}'", range))?;
+        let character = character
+            .chars()
+            .exactly_one()
+            .map_err(|_| anyhow!("Invalid policy char '{}'", character))?;
+
+        Ok(Self {
+            policy: Policy {
+                character,
+                least: least.parse()?,
+                most: most.parse()?,
+            },
+            password: password.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Password {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}-{} {}: {}",
+            self.policy.least, self.policy.most, self.policy.character, self.password
+        )
+    }
+}
+
+// A way of reading the numbers of a policy
+trait Interpretation {
+    fn name(&self) -> &'static str;
+
+    // Why the password doesn't follow the policy, or None if it does
+    fn check(&self, policy: &Policy, password: &str) -> Option<String>;
+}
+
+// The character must appear between `least` and `most` times
+struct CountRange;
+
+impl Interpretation for CountRange {
+    fn name(&self) -> &'static str {
+        "count"
+    }
+
+    fn check(&self, policy: &Policy, password: &str) -> Option<String> {
+        let count = password.chars().filter(|c| *c == policy.character).count();
+        if count >= policy.least && count <= policy.most {
+            None
+        } else {
+            Some(format!(
+                "'{}' appears {} times, expected {} to {}",
+                policy.character, count, policy.least, policy.most
+            ))
+        }
+    }
+}
+
+// The character must be at exactly one of the 1-based positions `least` and `most`
+struct ExactlyOnePosition;
+
+impl Interpretation for ExactlyOnePosition {
+    fn name(&self) -> &'static str {
+        "position"
+    }
+
+    fn check(&self, policy: &Policy, password: &str) -> Option<String> {
+        let at = |position: usize| {
+            position
+                .checked_sub(1)
+                .and_then(|i| password.chars().nth(i))
+                == Some(policy.character)
+        };
+
+        match (at(policy.least), at(policy.most)) {
+            (true, false) | (false, true) => None,
+            (true, true) => Some(format!(
+                "'{}' is at both positions {} and {}",
+                policy.character, policy.least, policy.most
+            )),
+            (false, false) => Some(format!(
+                "'{}' is at neither position {} nor {}",
+                policy.character, policy.least, policy.most
+            )),
+        }
+    }
+}
+
+const INTERPRETATIONS: [&dyn Interpretation; 2] = [&CountRange, &ExactlyOnePosition];
+
 struct Day2Solver(Vec<Password>);
 impl Solver for Day2Solver {
     fn part1(&self) -> Result<String> {
-        let passwords = &self.0;
-        let matches = passwords
-            .into_iter()
-            .filter(
-                |Password {
-                     policy,
-                     password: pw,
-                 }| {
-                    let count = pw.chars().filter(|c| *c == policy.character).count();
-                    count >= policy.least && count <= policy.most
-                },
-            )
-            .count();
-
-        Ok(format!("Matches: {}", matches))
+        Ok(format!("Matches: {}", self.matches(&CountRange)))
     }
 
     fn part2(&self) -> Result<String> {
-        let passwords = &self.0;
-        let matches = passwords
-            .into_iter()
-            .filter(
-                |Password {
-                     policy,
-                     password: pw,
-                 }| {
-                    let mut chars = pw.chars();
-                    let c1 = chars.nth(policy.least - 1);
-                    let c2 = chars.nth(policy.most - policy.least - 1);
-                    (c1 == Some(policy.character)) ^ (c2 == Some(policy.character))
-                },
-            )
-            .count();
-
-        Ok(format!("Matches: {}", matches))
+        Ok(format!("Matches: {}", self.matches(&ExactlyOnePosition)))
+    }
+
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "report" => {
+                let interpretations = match args.first() {
+                    Some(name) => vec![*INTERPRETATIONS
+                        .iter()
+                        .find(|interpretation| interpretation.name() == name)
+                        .ok_or(anyhow!(
+                            "Unknown policy '{}', expected {}",
+                            name,
+                            INTERPRETATIONS.iter().map(|i| i.name()).join(" or ")
+                        ))?],
+                    None => INTERPRETATIONS.to_vec(),
+                };
+
+                Ok(interpretations
+                    .iter()
+                    .map(|interpretation| self.report(*interpretation))
+                    .join("\n\n"))
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
     }
 }
 
-fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
-    Ok(Box::new(Day2Solver(
-        input
-            .lines()
-            .map(|line| -> Result<Password> {
-                let line = line?;
-                let mut parts = line.split(": ");
-
-                let policy_string = parts.next().ok_or(anyhow!("No policy"))?;
-                let mut policy_parts = policy_string.split(" ");
-                let range_string = policy_parts.next().ok_or(anyhow!("No policy range"))?;
-                let mut range_parts = range_string.split("-");
-                let least = range_parts
-                    .next()
-                    .ok_or(anyhow!("Invalid range"))?
-                    .parse()?;
-                let most = range_parts
-                    .next()
-                    .ok_or(anyhow!("Invalid range"))?
-                    .parse()?;
-                let character = policy_parts
-                    .next()
-                    .ok_or(anyhow!("No policy char"))?
-                    .chars()
-                    .next()
-                    .ok_or(anyhow!("Invalid policy char"))?;
-
-                let password = parts.next().ok_or(anyhow!("No password"))?.to_string();
-
-                Ok(Password {
-                    policy: Policy {
-                        character,
-                        least,
-                        most,
-                    },
-                    password,
-                })
+impl Day2Solver {
+    fn matches(&self, interpretation: &dyn Interpretation) -> usize {
+        self.0
+            .iter()
+            .filter(|password| {
+                interpretation
+                    .check(&password.policy, &password.password)
+                    .is_none()
             })
-            .collect::<Result<_, _>>()?,
-    )))
+            .count()
+    }
+
+    // Every password failing the policy under the interpretation, along with the reason
+    fn report(&self, interpretation: &dyn Interpretation) -> String {
+        let failures = self
+            .0
+            .iter()
+            .enumerate()
+            .filter_map(|(i, password)| {
+                let reason = interpretation.check(&password.policy, &password.password)?;
+                Some(format!("Line {}: {} ({})", i + 1, password, reason))
+            })
+            .collect_vec();
+
+        format!(
+            "Interpreting policies by {}, {} of {} passwords fail:\n{}",
+            interpretation.name(),
+            failures.len(),
+            self.0.len(),
+            failures.join("\n")
+        )
+    }
+}
+
+fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
+    let passwords = input
+        .lines()
+        .map(|line| line?.parse())
+        .collect::<Result<_>>()?;
+    Ok(Box::new(Day2Solver(passwords)))
 }
@@ -1,6 +1,8 @@
 use crate::day::{Day, DynSolver, Solver};
+use crate::grid::{Cell, Coords, Grid, ORTHOGONAL};
+use crate::image::{Color, Image, ToColor};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::{self, BufRead};
 
 use anyhow::{anyhow, bail, Result};
@@ -16,10 +18,170 @@ enum Pixel {
     Off,
 }
 
-#[derive(Debug)]
+impl Cell for Pixel {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '#' => Some(Self::On),
+            '.' => Some(Self::Off),
+            _ => None,
+        }
+    }
+
+    fn to_char(&self) -> char {
+        match self {
+            Self::On => '#',
+            Self::Off => '.',
+        }
+    }
+}
+
+impl ToColor for Pixel {
+    fn color(&self) -> Color {
+        match self {
+            Self::On => Color(150, 200, 255),
+            Self::Off => Color(10, 40, 110),
+        }
+    }
+}
+
+const SCALE: usize = 4;
+
+const SEA_MONSTER: [&str; 3] = [
+    "                  # ",
+    "#    ##    ##    ###",
+    " #  #  #  #  #  #   ",
+];
+
 struct Tile {
     id: u32,
-    edges: Vec<Vec<Pixel>>,
+    image: Grid<Pixel>,
+}
+
+// Whether the edges of two tiles line up when `other` is placed in the given direction of `tile`
+fn edges_match(tile: &Grid<Pixel>, other: &Grid<Pixel>, direction: (isize, isize)) -> bool {
+    match direction {
+        (1, 0) => tile.column(tile.width() - 1).eq(other.column(0)),
+        (-1, 0) => tile.column(0).eq(other.column(other.width() - 1)),
+        (0, 1) => tile.row(tile.height() - 1) == other.row(0),
+        (0, -1) => tile.row(0) == other.row(other.height() - 1),
+        _ => false,
+    }
+}
+
+fn assemble(tiles: &[Tile]) -> Result<Grid<Pixel>> {
+    fn place(
+        position: usize,
+        tiles: &[Tile],
+        used: &mut Vec<bool>,
+        arrangement: &mut Grid<Option<Grid<Pixel>>>,
+    ) -> bool {
+        let side = arrangement.width();
+        if position == side * side {
+            return true;
+        }
+
+        let coords = (position % side, position / side);
+        for (i, tile) in tiles.iter().enumerate() {
+            if used[i] {
+                continue;
+            }
+
+            for orientation in tile.image.orientations() {
+                let fits = arrangement.neighbors(coords, &ORTHOGONAL).all(|neighbor| {
+                    match arrangement.get(neighbor) {
+                        Some(Some(placed)) => {
+                            let direction = (
+                                neighbor.0 as isize - coords.0 as isize,
+                                neighbor.1 as isize - coords.1 as isize,
+                            );
+                            edges_match(&orientation, placed, direction)
+                        }
+                        _ => true,
+                    }
+                });
+                if !fits {
+                    continue;
+                }
+
+                used[i] = true;
+                if let Some(slot) = arrangement.get_mut(coords) {
+                    *slot = Some(orientation);
+                }
+                if place(position + 1, tiles, used, arrangement) {
+                    return true;
+                }
+                used[i] = false;
+                if let Some(slot) = arrangement.get_mut(coords) {
+                    *slot = None;
+                }
+            }
+        }
+
+        false
+    }
+
+    let side = (1..)
+        .find(|side| side * side >= tiles.len())
+        .filter(|side| side * side == tiles.len())
+        .ok_or(anyhow!("{} tiles cannot form a square", tiles.len()))?;
+    let mut arrangement = Grid::from_fn(side, side, |_| None);
+    if !place(0, tiles, &mut vec![false; tiles.len()], &mut arrangement) {
+        bail!("Tiles cannot be assembled");
+    }
+
+    let arrangement = Grid::from_fn(side, side, |coords| {
+        arrangement.get(coords).cloned().flatten().unwrap()
+    });
+    let tile_size = arrangement.get((0, 0)).map_or(0, |tile| tile.width()) - 2;
+    Ok(Grid::from_fn(
+        side * tile_size,
+        side * tile_size,
+        |(x, y)| {
+            let tile = arrangement.get((x / tile_size, y / tile_size)).unwrap();
+            *tile.get((x % tile_size + 1, y % tile_size + 1)).unwrap()
+        },
+    ))
+}
+
+fn find_sea_monsters(image: &Grid<Pixel>) -> HashSet<Coords> {
+    let monster = SEA_MONSTER
+        .iter()
+        .enumerate()
+        .flat_map(|(y, line)| {
+            line.chars()
+                .enumerate()
+                .filter(|(_, c)| *c == '#')
+                .map(move |(x, _)| (x, y))
+        })
+        .collect::<Vec<_>>();
+
+    let mut monster_pixels = HashSet::new();
+    for (x, y) in image.coords() {
+        let pixels = monster
+            .iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .collect::<Vec<_>>();
+        if pixels
+            .iter()
+            .all(|pixel| image.get(*pixel) == Some(&Pixel::On))
+        {
+            monster_pixels.extend(pixels);
+        }
+    }
+
+    monster_pixels
+}
+
+// The assembled image, oriented so that the sea monsters are visible, along with their pixels
+fn locate_sea_monsters(tiles: &[Tile]) -> Result<(Grid<Pixel>, HashSet<Coords>)> {
+    assemble(tiles)?
+        .orientations()
+        .map(|image| {
+            let monster_pixels = find_sea_monsters(&image);
+            (image, monster_pixels)
+        })
+        .find(|(_, monster_pixels)| !monster_pixels.is_empty())
+        .ok_or(anyhow!("No sea monsters found"))
 }
 
 struct Day20Solver(Vec<Tile>);
@@ -27,14 +189,9 @@ impl Solver for Day20Solver {
     fn part1(&self) -> Result<String> {
         let mut matches = HashMap::new();
         for tile in &self.0 {
-            for edge in &tile.edges {
-                matches.entry(edge.clone()).or_insert(vec![]).push(tile.id);
+            for orientation in tile.image.orientations() {
                 matches
-                    .entry({
-                        let mut v = edge.clone();
-                        v.reverse();
-                        v
-                    })
+                    .entry(orientation.row(0).to_vec())
                     .or_insert(vec![])
                     .push(tile.id);
             }
@@ -57,7 +214,32 @@ impl Solver for Day20Solver {
     }
 
     fn part2(&self) -> Result<String> {
-        bail!("Unimplemented")
+        let (image, monster_pixels) = locate_sea_monsters(&self.0)?;
+        let roughness =
+            image.iter().filter(|pixel| **pixel == Pixel::On).count() - monster_pixels.len();
+
+        Ok(format!("Water roughness: {}", roughness))
+    }
+
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "export" => {
+                let path = args.first().ok_or(anyhow!("Missing output file"))?;
+
+                let (image, monster_pixels) = locate_sea_monsters(&self.0)?;
+                Image::from_grid(&image, SCALE, |coords, pixel| {
+                    if monster_pixels.contains(&coords) {
+                        Color(40, 200, 80)
+                    } else {
+                        pixel.color()
+                    }
+                })
+                .save(path)?;
+
+                Ok(format!("Saved image to {}", path))
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
     }
 }
 
@@ -65,7 +247,7 @@ fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
     let mut tiles = Vec::new();
     loop {
         let mut lines = input.lines().peekable();
-        if let None = lines.peek() {
+        if lines.peek().is_none() {
             break;
         }
 
@@ -80,40 +262,9 @@ fn parse_tile(mut lines: impl Iterator<Item = Result<String, io::Error>>) -> Res
     let header = lines.next().ok_or(anyhow!("No header"))??;
     let id = header[5..(header.len() - 1)].parse()?;
     let image_data = lines
-        .take_while(|line| {
-            if let Ok("") = line.as_ref().map(|s| s.as_str()) {
-                false
-            } else {
-                true
-            }
-        })
-        .map(|line| {
-            Ok(line?
-                .chars()
-                .map(|c| if c == '#' { Pixel::On } else { Pixel::Off })
-                .collect::<Vec<_>>())
-        })
-        .collect::<Result<Vec<_>>>()?;
-
-    let top = image_data[0].clone();
-    let left = image_data
-        .iter()
-        .map(|row| &row[0])
-        .rev()
-        .cloned()
-        .collect();
-    let right = image_data
-        .iter()
-        .map(|row| &row[row.len() - 1])
-        .cloned()
-        .collect();
-    let bottom = image_data[image_data.len() - 1]
-        .iter()
-        .rev()
-        .cloned()
-        .collect();
-
-    let edges = vec![top, left, right, bottom];
+        .take_while(|line| !matches!(line.as_ref().map(|s| s.as_str()), Ok("")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let image = Grid::parse_lines(image_data)?;
 
-    Ok(Tile { id, edges })
+    Ok(Tile { id, image })
 }
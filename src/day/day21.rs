@@ -1,9 +1,10 @@
 use crate::day::{Day, DynSolver, Solver};
+use crate::matching::Matching;
 
 use std::collections::{HashMap, HashSet};
 use std::io::BufRead;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use itertools::Itertools;
 
 pub const DAY21: Day = Day {
@@ -23,52 +24,71 @@ struct AllergenTable<'a> {
 }
 
 impl<'a> AllergenTable<'a> {
-    fn compile(food_items: &'a [FoodItem]) -> Self {
-        let mut possible_allergens = HashMap::<&str, HashSet<&str>>::new();
-        for food_item in food_items {
-            for allergen in &food_item.allergens {
-                possible_allergens
-                    .entry(allergen)
-                    .and_modify(|ingredients| {
-                        ingredients.retain(|ingredient| {
-                            food_item.ingredients.iter().any(|x| x == ingredient)
-                        })
-                    })
-                    .or_insert_with(|| food_item.ingredients.iter().map(|s| s.as_str()).collect());
-            }
-        }
-
-        let mut allergens = HashMap::new();
-        let mut ingredients_with_allergen = HashSet::new();
-        while allergens.len() != possible_allergens.len() {
-            for (allergen, ingredients) in &possible_allergens {
-                if allergens.contains_key(allergen) {
-                    continue;
-                }
-
-                let the_one_ingredient = ingredients
-                    .iter()
-                    .filter(|ingredient| !ingredients_with_allergen.contains(*ingredient))
-                    .exactly_one();
-
-                if let Ok(matched_ingredient) = the_one_ingredient {
-                    allergens.insert(*allergen, *matched_ingredient);
-                    ingredients_with_allergen.insert(*matched_ingredient);
-                }
-            }
-        }
-
-        Self {
+    fn compile(food_items: &'a [FoodItem]) -> Result<Self> {
+        let matching = Matching::new(candidates(food_items));
+        let elimination = matching.eliminate();
+        let allergens = if elimination.remaining.is_empty() {
+            elimination.assigned
+        } else {
+            // Elimination alone is stuck, but the allergens may still be pinned down by trying
+            // every remaining candidate
+            matching.solve().unique().map_err(|e| {
+                anyhow!(
+                    "{}, remaining candidates after elimination: {}",
+                    e,
+                    describe_candidates(&elimination.remaining, "; ")
+                )
+            })?
+        };
+        let allergens = allergens.into_iter().collect::<HashMap<_, _>>();
+        let ingredients_with_allergen = allergens.values().copied().collect();
+
+        Ok(Self {
             allergens,
             ingredients_with_allergen,
+        })
+    }
+}
+
+// Every allergen along with the ingredients found in every food item that contains it
+fn candidates(food_items: &[FoodItem]) -> Vec<(&str, Vec<&str>)> {
+    let mut possible_allergens = HashMap::<&str, HashSet<&str>>::new();
+    for food_item in food_items {
+        for allergen in &food_item.allergens {
+            possible_allergens
+                .entry(allergen)
+                .and_modify(|ingredients| {
+                    ingredients
+                        .retain(|ingredient| food_item.ingredients.iter().any(|x| x == ingredient))
+                })
+                .or_insert_with(|| food_item.ingredients.iter().map(|s| s.as_str()).collect());
         }
     }
+
+    possible_allergens
+        .into_iter()
+        .sorted_by_key(|(allergen, _)| *allergen)
+        .map(|(allergen, ingredients)| (allergen, ingredients.into_iter().sorted().collect()))
+        .collect()
+}
+
+fn describe_candidates(candidates: &[(&str, Vec<&str>)], separator: &str) -> String {
+    candidates
+        .iter()
+        .map(|(allergen, ingredients)| {
+            if ingredients.is_empty() {
+                format!("{}: none", allergen)
+            } else {
+                format!("{}: {}", allergen, ingredients.iter().join(" or "))
+            }
+        })
+        .join(separator)
 }
 
 struct Day21Solver(Vec<FoodItem>);
 impl Solver for Day21Solver {
     fn part1(&self) -> Result<String> {
-        let allergen_table = AllergenTable::compile(&self.0);
+        let allergen_table = AllergenTable::compile(&self.0)?;
 
         let result = self
             .0
@@ -85,7 +105,7 @@ impl Solver for Day21Solver {
     }
 
     fn part2(&self) -> Result<String> {
-        let allergen_table = AllergenTable::compile(&self.0);
+        let allergen_table = AllergenTable::compile(&self.0)?;
 
         let result = allergen_table
             .allergens
@@ -96,6 +116,43 @@ impl Solver for Day21Solver {
 
         Ok(format!("Canonical dangerous ingredient list: {}", result))
     }
+
+    fn command(&self, name: &str, _args: &[String]) -> Result<String> {
+        match name {
+            "explain" => {
+                let candidates = candidates(&self.0);
+                let elimination = Matching::new(candidates.clone()).eliminate();
+
+                let order = if elimination.assigned.is_empty() {
+                    "No allergen has a single candidate".to_string()
+                } else {
+                    elimination
+                        .assigned
+                        .iter()
+                        .enumerate()
+                        .map(|(i, (allergen, ingredient))| {
+                            format!("{}. {}: {}", i + 1, allergen, ingredient)
+                        })
+                        .join("\n")
+                };
+
+                let mut explanation = format!(
+                    "Candidates after intersecting the foods containing every allergen:\n{}\n\nElimination order:\n{}",
+                    describe_candidates(&candidates, "\n"),
+                    order
+                );
+                if !elimination.remaining.is_empty() {
+                    explanation += &format!(
+                        "\n\nStuck with remaining candidates:\n{}",
+                        describe_candidates(&elimination.remaining, "\n")
+                    );
+                }
+
+                Ok(explanation)
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
+    }
 }
 
 fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
@@ -1,10 +1,12 @@
 use crate::day::{Day, DynSolver, Solver};
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::BufRead;
+use std::iter;
 use std::marker::PhantomData;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use itertools::Itertools;
 
 pub const DAY22: Day = Day {
     title: "Crab Combat",
@@ -21,12 +23,21 @@ impl GameMode for Combat {}
 struct RecursiveCombat;
 impl GameMode for RecursiveCombat {}
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 enum Player {
     Player1,
     Player2,
 }
 
+impl Player {
+    fn number(self) -> u8 {
+        match self {
+            Self::Player1 => 1,
+            Self::Player2 => 2,
+        }
+    }
+}
+
 struct Winner {
     player: Player,
     score: u32,
@@ -37,6 +48,128 @@ enum GameState {
     Finished(Winner),
 }
 
+// Statistics of a game and all its sub-games, optionally logging every round as it's played
+struct Record {
+    log: bool,
+    lines: Vec<String>,
+    // Whether to remember the winners of sub-games instead of playing them again
+    memo: bool,
+    // Whether to skip sub-games where player 1 holds the highest card
+    shortcut: bool,
+    // Sub-games are only played for their winner, so winners are remembered by the starting decks
+    sub_game_winners: HashMap<Vec<u32>, Player>,
+    // The number of every game in progress, along with its current round, innermost last
+    games_in_progress: Vec<(usize, usize)>,
+    games: usize,
+    rounds: usize,
+    max_depth: usize,
+    repeated_state_wins: usize,
+    remembered_sub_games: usize,
+    shortcut_sub_games: usize,
+}
+
+impl Record {
+    fn new(log: bool, memo: bool, shortcut: bool) -> Self {
+        Self {
+            log,
+            lines: Vec::new(),
+            memo,
+            shortcut,
+            sub_game_winners: HashMap::new(),
+            games_in_progress: Vec::new(),
+            games: 0,
+            rounds: 0,
+            max_depth: 0,
+            repeated_state_wins: 0,
+            remembered_sub_games: 0,
+            shortcut_sub_games: 0,
+        }
+    }
+
+    fn log(&mut self, message: impl FnOnce() -> String) {
+        if self.log {
+            self.lines.push(message());
+        }
+    }
+
+    fn current_game(&self) -> (usize, usize) {
+        self.games_in_progress.last().copied().unwrap_or((0, 0))
+    }
+
+    fn start_game(&mut self) {
+        self.games += 1;
+        self.games_in_progress.push((self.games, 0));
+        self.max_depth = self.max_depth.max(self.games_in_progress.len());
+        let game = self.games;
+        self.log(|| format!("=== Game {} ===\n", game));
+    }
+
+    fn start_round(&mut self, player1: &VecDeque<u32>, player2: &VecDeque<u32>) {
+        self.rounds += 1;
+        if let Some((_, round)) = self.games_in_progress.last_mut() {
+            *round += 1;
+        }
+        let (game, round) = self.current_game();
+        self.log(|| {
+            format!(
+                "-- Round {} (Game {}) --\nPlayer 1's deck: {}\nPlayer 2's deck: {}",
+                round,
+                game,
+                player1.iter().join(", "),
+                player2.iter().join(", ")
+            )
+        });
+    }
+
+    fn finish_round(&mut self, winner: Player) {
+        let (game, round) = self.current_game();
+        self.log(|| {
+            format!(
+                "Player {} wins round {} of game {}!\n",
+                winner.number(),
+                round,
+                game
+            )
+        });
+    }
+
+    fn finish_game(&mut self, winner: Player, player1: &VecDeque<u32>, player2: &VecDeque<u32>) {
+        let (game, _) = self.current_game();
+        self.games_in_progress.pop();
+        self.log(|| {
+            format!(
+                "The winner of game {} is player {}!\n",
+                game,
+                winner.number()
+            )
+        });
+
+        match self.games_in_progress.last().copied() {
+            Some((game, _)) => self.log(|| format!("...anyway, back to game {}.", game)),
+            None => self.log(|| {
+                format!(
+                    "== Post-game results ==\nPlayer 1's deck: {}\nPlayer 2's deck: {}\n",
+                    player1.iter().join(", "),
+                    player2.iter().join(", ")
+                )
+            }),
+        }
+    }
+
+    fn stats(&self) -> String {
+        format!(
+            "Games: {}\nRounds: {}\nMaximum depth: {}\nGames won by repeated state: {}\n\
+             Sub-games remembered: {}\nSub-games won by the highest card: {}",
+            self.games,
+            self.rounds,
+            self.max_depth,
+            self.repeated_state_wins,
+            self.remembered_sub_games,
+            self.shortcut_sub_games
+        )
+    }
+}
+
 #[derive(Clone, PartialEq)]
 struct Game<Mode: GameMode> {
     player1: VecDeque<u32>,
@@ -66,6 +199,14 @@ impl<Mode: GameMode> Game<Mode> {
             .sum()
     }
 
+    // Both decks in one vector, starting with the length of the first one
+    fn state(&self) -> Vec<u32> {
+        iter::once(self.player1.len() as u32)
+            .chain(self.player1.iter().copied())
+            .chain(self.player2.iter().copied())
+            .collect()
+    }
+
     fn win(&self, winner: Player) -> Winner {
         Winner {
             player: winner,
@@ -75,7 +216,8 @@ impl<Mode: GameMode> Game<Mode> {
 }
 
 impl Game<Combat> {
-    fn do_round(&mut self) -> GameState {
+    fn do_round(&mut self, record: &mut Record) -> GameState {
+        record.start_round(&self.player1, &self.player2);
         let player1_card = if let Some(card) = self.player1.pop_front() {
             card
         } else {
@@ -88,12 +230,21 @@ impl Game<Combat> {
             return GameState::Finished(self.win(Player::Player1));
         };
 
+        record.log(|| {
+            format!(
+                "Player 1 plays: {}\nPlayer 2 plays: {}",
+                player1_card, player2_card
+            )
+        });
+
         if player1_card > player2_card {
             self.player1.push_back(player1_card);
             self.player1.push_back(player2_card);
+            record.finish_round(Player::Player1);
         } else {
             self.player2.push_back(player2_card);
             self.player2.push_back(player1_card);
+            record.finish_round(Player::Player2);
         }
 
         if self.player1.is_empty() {
@@ -105,18 +256,23 @@ impl Game<Combat> {
         }
     }
 
-    fn play_to_end(mut self) -> Winner {
-        loop {
-            let game_state = self.do_round();
+    fn play_to_end(mut self, record: &mut Record) -> Winner {
+        record.start_game();
+        let winner = loop {
+            let game_state = self.do_round(record);
             if let GameState::Finished(winner) = game_state {
                 break winner;
             }
-        }
+        };
+        record.finish_game(winner.player, &self.player1, &self.player2);
+
+        winner
     }
 }
 
 impl Game<RecursiveCombat> {
-    fn do_round(&mut self) -> GameState {
+    fn do_round(&mut self, record: &mut Record) -> GameState {
+        record.start_round(&self.player1, &self.player2);
         let player1_card = if let Some(card) = self.player1.pop_front() {
             card
         } else {
@@ -129,6 +285,13 @@ impl Game<RecursiveCombat> {
             return GameState::Finished(self.win(Player::Player1));
         };
 
+        record.log(|| {
+            format!(
+                "Player 1 plays: {}\nPlayer 2 plays: {}",
+                player1_card, player2_card
+            )
+        });
+
         let winner = if self.player1.len() >= player1_card as usize
             && self.player2.len() >= player2_card as usize
         {
@@ -144,7 +307,7 @@ impl Game<RecursiveCombat> {
                     .copied()
                     .collect(),
             );
-            subgame.play_to_end().player
+            subgame.play_sub_game(record)
         } else if player1_card > player2_card {
             Player::Player1
         } else {
@@ -161,6 +324,7 @@ impl Game<RecursiveCombat> {
                 self.player2.push_back(player1_card);
             }
         };
+        record.finish_round(winner);
 
         if self.player1.is_empty() {
             GameState::Finished(self.win(Player::Player2))
@@ -171,19 +335,61 @@ impl Game<RecursiveCombat> {
         }
     }
 
-    fn play_to_end(mut self) -> Winner {
-        let mut history = Vec::new();
-        loop {
-            if history.contains(&self) {
+    fn play_sub_game(self, record: &mut Record) -> Player {
+        let state = self.state();
+        let remembered = if record.memo {
+            record.sub_game_winners.get(&state).copied()
+        } else {
+            None
+        };
+        if let Some(winner) = remembered {
+            record.remembered_sub_games += 1;
+            record.log(|| {
+                format!(
+                    "This sub-game was played before, player {} wins it.",
+                    winner.number()
+                )
+            });
+            return winner;
+        }
+
+        // Cards are unique, so the highest card is at least as large as the amount of cards in
+        // the game. Its holder can then never have enough cards left to recurse when playing it,
+        // so they never lose it, and thus never lose the game
+        let highest1 = self.player1.iter().max();
+        let winner = if record.shortcut && highest1 > self.player2.iter().max() {
+            record.shortcut_sub_games += 1;
+            record.log(|| "Player 1 holds the highest card, and wins the sub-game.".to_string());
+            Player::Player1
+        } else {
+            record.log(|| "Playing a sub-game to determine the winner...\n".to_string());
+            self.play_to_end(record).player
+        };
+
+        if record.memo {
+            record.sub_game_winners.insert(state, winner);
+        }
+        winner
+    }
+
+    fn play_to_end(mut self, record: &mut Record) -> Winner {
+        record.start_game();
+        let mut history = HashSet::new();
+        let winner = loop {
+            if !history.insert(self.state()) {
+                record.repeated_state_wins += 1;
+                record.log(|| "The decks repeat a previous round, so player 1 wins!".to_string());
                 break self.win(Player::Player1);
             }
 
-            history.push(self.clone());
-            let game_state = self.do_round();
+            let game_state = self.do_round(record);
             if let GameState::Finished(winner) = game_state {
                 break winner;
             }
-        }
+        };
+        record.finish_game(winner.player, &self.player1, &self.player2);
+
+        winner
     }
 }
 
@@ -191,23 +397,89 @@ struct Day22Solver {
     player1: Vec<u32>,
     player2: Vec<u32>,
 }
+
+impl Day22Solver {
+    fn play(&self, part: &str, record: &mut Record) -> Result<Winner> {
+        let player1 = self.player1.clone().into();
+        let player2 = self.player2.clone().into();
+        Ok(match part {
+            "1" => Game::<Combat>::new(player1, player2).play_to_end(record),
+            "2" => Game::<RecursiveCombat>::new(player1, player2).play_to_end(record),
+            _ => bail!("Unknown part '{}'", part),
+        })
+    }
+}
+
 impl Solver for Day22Solver {
     fn part1(&self) -> Result<String> {
-        let game = Game::<Combat>::new(self.player1.clone().into(), self.player2.clone().into());
-
-        let winner_score = game.play_to_end().score;
+        let winner_score = self.play("1", &mut Record::new(false, true, false))?.score;
 
         Ok(format!("Winner score: {}", winner_score))
     }
 
     fn part2(&self) -> Result<String> {
-        let game =
-            Game::<RecursiveCombat>::new(self.player1.clone().into(), self.player2.clone().into());
-
-        let winner_score = game.play_to_end().score;
+        let winner_score = self.play("2", &mut Record::new(false, true, false))?.score;
 
         Ok(format!("Winner score: {}", winner_score))
     }
+
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        let memo = args.iter().any(|arg| arg == "--memo");
+        let shortcut = args.iter().any(|arg| arg == "--shortcut");
+        let part = args
+            .iter()
+            .find(|arg| *arg != "--memo" && *arg != "--shortcut")
+            .map_or("2", |s| s.as_str());
+
+        match name {
+            "replay" | "stats" => {
+                let mut record = Record::new(name == "replay", memo, shortcut);
+                let winner = self.play(part, &mut record)?;
+
+                Ok(record
+                    .lines
+                    .iter()
+                    .cloned()
+                    .chain(iter::once(format!(
+                        "Player {} wins with score {}\n{}",
+                        winner.player.number(),
+                        winner.score,
+                        record.stats()
+                    )))
+                    .join("\n"))
+            }
+            "check" => {
+                let mut full = Record::new(false, true, false);
+                let full_winner = self.play(part, &mut full)?;
+                let mut shortcut = Record::new(false, true, true);
+                let shortcut_winner = self.play(part, &mut shortcut)?;
+
+                if full_winner.player != shortcut_winner.player
+                    || full_winner.score != shortcut_winner.score
+                {
+                    bail!(
+                        "Player {} wins with score {} when playing every sub-game, \
+                         but player {} wins with score {} with the shortcut",
+                        full_winner.player.number(),
+                        full_winner.score,
+                        shortcut_winner.player.number(),
+                        shortcut_winner.score
+                    );
+                }
+
+                Ok(format!(
+                    "Player {} wins with score {} either way\n\
+                     Playing every sub-game:\n{}\n\
+                     With the shortcut:\n{}",
+                    full_winner.player.number(),
+                    full_winner.score,
+                    full.stats(),
+                    shortcut.stats()
+                ))
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
+    }
 }
 
 fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
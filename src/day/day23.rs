@@ -1,10 +1,9 @@
 use crate::day::{Day, DynSolver, Solver};
 
-use std::collections::HashMap;
 use std::io::BufRead;
 use std::iter;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use itertools::Itertools;
 
 pub const DAY23: Day = Day {
@@ -12,84 +11,146 @@ pub const DAY23: Day = Day {
     solver_from_input,
 };
 
-struct Day23Solver(Vec<u32>);
-impl Solver for Day23Solver {
-    fn part1(&self) -> Result<String> {
-        let mut cups = self.0.clone();
-        for _ in 0..100 {
-            let mut yanked = [0; 3];
-            yanked.copy_from_slice(&cups[1..4]);
-            cups.drain(1..4);
-
-            let mut destination_label = cups[0] - 1;
-            while !cups.contains(&destination_label) {
-                destination_label = if destination_label == 0 {
-                    9
-                } else {
-                    destination_label - 1
-                }
-            }
-            let destination_index =
-                1 + cups
-                    .iter()
-                    .position(|n| *n == destination_label)
-                    .ok_or(anyhow!(
-                        "Unable to find cup with label {}",
-                        destination_label,
-                    ))?;
-            cups.splice(destination_index..destination_index, yanked.iter().copied());
-            cups.rotate_left(1);
+// Amount of cups shown when printing the state of the ring
+const SHOWN_CUPS: usize = 30;
+
+// The ring of cups, stored as the label of the cup clockwise of every cup, indexed by label
+struct CupRing {
+    next: Vec<u32>,
+    current: u32,
+}
+
+impl CupRing {
+    // The cups with the given labels, followed by cups with increasing labels up to the given
+    // total amount of cups
+    fn new(labels: &[u32], total: usize) -> Result<Self> {
+        if labels.is_empty() {
+            bail!("No cups");
+        }
+        if labels.iter().copied().sorted().ne(1..=labels.len() as u32) {
+            bail!("Cups must be labelled 1 to {}", labels.len());
+        }
+        if total < labels.len() {
+            bail!("Can't have {} cups with {} labelled", total, labels.len());
+        }
+        // The current cup and the three picked up ones
+        if total < 4 {
+            bail!("At least 4 cups are needed");
         }
 
-        let result = cups
+        let mut next = vec![0; total + 1];
+        let labels = labels
             .iter()
-            .cycle()
-            .skip_while(|n| **n != 1)
-            .skip(1)
-            .take(cups.len() - 1)
-            .join("");
+            .copied()
+            .chain((labels.len() as u32 + 1)..=(total as u32))
+            .collect_vec();
+        for (cup, next_cup) in labels.iter().zip(labels.iter().cycle().skip(1)) {
+            next[*cup as usize] = *next_cup;
+        }
 
-        Ok(format!("Labels after cup 1: {}", result))
+        Ok(Self {
+            next,
+            current: labels[0],
+        })
     }
 
-    fn part2(&self) -> Result<String> {
-        let mut cups = self
-            .0
-            .iter()
-            .copied()
-            .chain(10..=1_000_000)
-            .chain(iter::once(self.0[0]))
-            .tuple_windows()
-            .collect::<HashMap<_, _>>();
-        let mut current = self.0[0];
-        for _ in 0..10_000_000 {
-            let a = cups[&current];
-            let b = cups[&a];
-            let c = cups[&b];
-            let yanked = [a, b, c];
-            cups.insert(current, cups[&c]);
-
-            let mut destination = current - 1;
-            while destination == 0 || yanked.contains(&destination) {
-                destination = if destination == 0 {
-                    1_000_000
-                } else {
-                    destination - 1
-                }
+    fn len(&self) -> usize {
+        self.next.len() - 1
+    }
+
+    fn play_move(&mut self) {
+        let a = self.next[self.current as usize];
+        let b = self.next[a as usize];
+        let c = self.next[b as usize];
+        let picked_up = [a, b, c];
+        self.next[self.current as usize] = self.next[c as usize];
+
+        let max = self.len() as u32;
+        let mut destination = self.current;
+        loop {
+            destination = if destination == 1 {
+                max
+            } else {
+                destination - 1
+            };
+            if !picked_up.contains(&destination) {
+                break;
             }
+        }
 
-            let after = cups[&destination];
-            cups.insert(destination, a);
-            cups.insert(c, after);
-            current = cups[&current];
+        self.next[c as usize] = self.next[destination as usize];
+        self.next[destination as usize] = a;
+        self.current = self.next[self.current as usize];
+    }
+
+    fn play(&mut self, moves: usize) {
+        for _ in 0..moves {
+            self.play_move();
         }
+    }
 
-        let star1 = cups[&1];
-        let star2 = cups[&star1];
+    // Every other cup, clockwise from the given one
+    fn after(&self, cup: u32) -> impl Iterator<Item = u32> + '_ {
+        iter::successors(Some(self.next[cup as usize]), move |cup| {
+            Some(self.next[*cup as usize])
+        })
+        .take(self.len() - 1)
+    }
+
+    // The cups clockwise from the current one, which is in parentheses
+    fn state(&self) -> String {
+        let mut state = iter::once(format!("({})", self.current))
+            .chain(self.after(self.current).map(|cup| cup.to_string()))
+            .take(SHOWN_CUPS)
+            .join(" ");
+        if self.len() > SHOWN_CUPS {
+            state.push_str(" ...");
+        }
+        state
+    }
+}
+
+struct Day23Solver(Vec<u32>);
+impl Solver for Day23Solver {
+    fn part1(&self) -> Result<String> {
+        let mut cups = CupRing::new(&self.0, self.0.len())?;
+        cups.play(100);
+
+        let result = cups.after(1).join("");
+
+        Ok(format!("Labels after cup 1: {}", result))
+    }
+
+    fn part2(&self) -> Result<String> {
+        let mut cups = CupRing::new(&self.0, 1_000_000)?;
+        cups.play(10_000_000);
+
+        let (star1, star2) = cups
+            .after(1)
+            .next_tuple()
+            .ok_or(anyhow!("Not enough cups to hide the stars"))?;
         let result = star1 as u64 * star2 as u64;
 
         Ok(format!("Product of cups hiding stars: {}", result))
     }
+
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "play" => {
+                let moves = args
+                    .first()
+                    .ok_or(anyhow!("Missing amount of moves"))?
+                    .parse()?;
+                let total = args.get(1).map_or(Ok(self.0.len()), |s| s.parse())?;
+
+                let mut cups = CupRing::new(&self.0, total)?;
+                cups.play(moves);
+
+                Ok(format!("Cups after {} moves: {}", moves, cups.state()))
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
+    }
 }
 
 fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
@@ -1,132 +1,267 @@
+use crate::animation::Animation;
+use crate::automaton::LifeRule;
 use crate::day::{Day, DynSolver, Solver};
+use crate::grid::Grid;
+use crate::hex::{Axial, Direction, Offset};
+use crate::image::{Color, Image};
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::io::BufRead;
 
 use anyhow::{anyhow, bail, Result};
 use itertools::Itertools;
 
+const DAYS: usize = 100;
+const SCALE: usize = 4;
+
 pub const DAY24: Day = Day {
     title: "Lobby Layout",
     solver_from_input,
 };
 
-#[derive(Clone, Copy)]
-enum Direction {
-    W,
-    E,
-    NW,
-    NE,
-    SW,
-    SE,
+// The lobby floor, where black tiles flip according to how many of their neighbors are black
+struct Lobby {
+    black_tiles: HashSet<Axial>,
+    rule: LifeRule,
 }
 
-fn setup(instructions: &Vec<Vec<Direction>>) -> HashSet<(i32, i32)> {
-    instructions
-        .iter()
-        .map(|steps| {
-            steps
-                .iter()
-                .fold((0, 0), |coords, dir| adjacent(coords, *dir))
-        })
-        .counts()
-        .into_iter()
-        .filter(|(_, count)| count % 2 == 1)
-        .map(|(coords, _)| coords)
-        .collect()
+impl Lobby {
+    fn new(paths: &[Vec<Direction>], rule: LifeRule) -> Self {
+        let black_tiles = paths
+            .iter()
+            .map(|path| Axial::ORIGIN.walk(path))
+            .counts()
+            .into_iter()
+            .filter(|(_, count)| count % 2 == 1)
+            .map(|(coords, _)| coords)
+            .collect();
+
+        Self { black_tiles, rule }
+    }
+
+    fn step(&mut self) {
+        // Black tiles without black neighbors must still be checked for survival
+        let mut black_neighbors = self
+            .black_tiles
+            .iter()
+            .map(|coords| (*coords, 0))
+            .collect::<HashMap<_, _>>();
+        for coords in &self.black_tiles {
+            for neighbor in coords.neighbors() {
+                *black_neighbors.entry(neighbor).or_insert(0) += 1;
+            }
+        }
+
+        let black_tiles = &self.black_tiles;
+        let rule = &self.rule;
+        self.black_tiles = black_neighbors
+            .into_iter()
+            .filter(|(coords, count)| rule.next(black_tiles.contains(coords), *count))
+            .map(|(coords, _)| coords)
+            .collect();
+    }
+
+    fn run(&mut self, days: usize) {
+        for _ in 0..days {
+            self.step();
+        }
+    }
+}
+
+// Horizontal positions in half tiles, as odd rows are shifted half a tile to the east
+fn half_tile_x(Offset { column, row }: Offset) -> i32 {
+    2 * column + (row & 1)
+}
+
+// The bounds of the half tile positions and of the rows of the given tiles
+fn bounds(tiles: &HashSet<Axial>) -> Option<((i32, i32), (i32, i32))> {
+    let offsets = tiles.iter().map(|coords| coords.to_offset()).collect_vec();
+    let xs = offsets.iter().map(|offset| half_tile_x(*offset)).minmax();
+    let rows = offsets.iter().map(|offset| offset.row).minmax();
+    xs.into_option().zip(rows.into_option())
 }
 
-fn adjacent((x, y): (i32, i32), dir: Direction) -> (i32, i32) {
-    match dir {
-        Direction::W => (x - 1, y),
-        Direction::E => (x + 1, y),
-        Direction::NW => (x, y + 1),
-        Direction::NE => (x + 1, y + 1),
-        Direction::SW => (x - 1, y - 1),
-        Direction::SE => (x, y - 1),
+// The tile covering the given half tile position
+fn tile_at(x: i32, row: i32) -> Axial {
+    Offset {
+        column: (x - (row & 1)).div_euclid(2),
+        row,
     }
+    .to_axial()
 }
 
-fn adjacents(coords: (i32, i32)) -> impl Iterator<Item = (i32, i32)> {
-    use Direction::*;
+// Draws black tiles as '#' and white as '.', with a space between every tile so that odd rows can
+// be shifted by half a tile
+fn render(tiles: &HashSet<Axial>) -> String {
+    let ((min_x, max_x), (min_row, max_row)) = match bounds(tiles) {
+        Some(bounds) => bounds,
+        None => return String::new(),
+    };
 
-    [W, E, NW, NE, SW, SE]
-        .iter()
-        .map(move |dir| adjacent(coords, *dir))
+    (min_row..=max_row)
+        .map(|row| {
+            (min_x..=max_x)
+                .map(|x| {
+                    if (x - (row & 1)).rem_euclid(2) != 0 {
+                        ' '
+                    } else if tiles.contains(&tile_at(x, row)) {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>()
+        })
+        .join("\n")
+}
+
+// Lays the tiles out like bricks two cells wide, where odd rows are shifted by one cell, telling
+// whether each cell is covered by a black tile
+fn brick_layout(tiles: &HashSet<Axial>) -> Grid<bool> {
+    let ((min_x, max_x), (min_row, max_row)) = match bounds(tiles) {
+        Some(bounds) => bounds,
+        None => return Grid::from_fn(0, 0, |_| false),
+    };
+
+    Grid::from_fn(
+        (max_x - min_x + 2) as usize,
+        (max_row - min_row + 1) as usize,
+        |(x, row)| tiles.contains(&tile_at(x as i32 + min_x, row as i32 + min_row)),
+    )
 }
 
 struct Day24Solver(Vec<Vec<Direction>>);
+
+impl Day24Solver {
+    // Black tiles with one or two black neighbors stay black, and white tiles with exactly two
+    // black neighbors turn black
+    fn lobby_rule() -> LifeRule {
+        LifeRule::new(&[2], &[1, 2])
+    }
+
+    // The lobby after the given number of days, with the days and rule optionally given in the
+    // arguments
+    fn lobby_from_args(&self, args: &[String]) -> Result<(Lobby, usize)> {
+        let days = args.first().map_or(Ok(DAYS), |s| s.parse())?;
+        let rule = args
+            .get(1)
+            .map_or(Ok(Self::lobby_rule()), |s| s.parse::<LifeRule>())?;
+        if rule.births_from_nothing() {
+            bail!("Rules with birth on 0 neighbors would turn the whole endless floor black");
+        }
+
+        Ok((Lobby::new(&self.0, rule), days))
+    }
+}
+
 impl Solver for Day24Solver {
     fn part1(&self) -> Result<String> {
-        let black_count = setup(&self.0).len();
+        let black_count = Lobby::new(&self.0, Self::lobby_rule()).black_tiles.len();
 
         Ok(format!("Amount of flipped tiles: {}", black_count))
     }
 
     fn part2(&self) -> Result<String> {
-        let mut tiles = setup(&self.0);
-
-        for _ in 0..100 {
-            let mut visited_white = Vec::new();
-            let mut new_tiles = tiles
-                .iter()
-                .copied()
-                .filter(|coords| {
-                    let (adjacent_black, adjacent_white): (Vec<_>, _) =
-                        adjacents(*coords).partition(|adjacent| tiles.contains(&adjacent));
-                    let adjacent_black_count = adjacent_black.len();
-                    visited_white.extend(adjacent_white);
-
-                    adjacent_black_count == 1 || adjacent_black_count == 2
-                })
-                .collect::<HashSet<_>>();
-            new_tiles.extend(
-                visited_white
-                    .into_iter()
-                    .counts()
-                    .into_iter()
-                    .filter(|(_, count)| *count == 2)
-                    .map(|(coords, _)| coords),
-            );
-
-            tiles = new_tiles;
-        }
+        let mut lobby = Lobby::new(&self.0, Self::lobby_rule());
+        lobby.run(DAYS);
 
-        let black_count = tiles.len();
+        let black_count = lobby.black_tiles.len();
 
         Ok(format!("Amount of black tiles: {}", black_count))
     }
+
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "run" => {
+                let (mut lobby, days) = self.lobby_from_args(args)?;
+                lobby.run(days);
+
+                Ok(format!(
+                    "Amount of black tiles after {} days: {}",
+                    days,
+                    lobby.black_tiles.len()
+                ))
+            }
+            "rings" => {
+                let (mut lobby, days) = self.lobby_from_args(args)?;
+                lobby.run(days);
+
+                let radius = lobby
+                    .black_tiles
+                    .iter()
+                    .map(|coords| coords.distance(Axial::ORIGIN))
+                    .max()
+                    .unwrap_or(0);
+                Ok((0..=radius)
+                    .map(|radius| {
+                        let ring = Axial::ORIGIN.ring(radius);
+                        let black = ring
+                            .iter()
+                            .filter(|coords| lobby.black_tiles.contains(coords))
+                            .count();
+                        format!("Distance {}: {}/{} black", radius, black, ring.len())
+                    })
+                    .join("\n"))
+            }
+            "locate" => {
+                let path = Direction::parse_path(args.first().ok_or(anyhow!("Missing path"))?)?;
+                let lobby = Lobby::new(&self.0, Self::lobby_rule());
+
+                let coords = Axial::ORIGIN.walk(&path);
+                let line = coords.line(Axial::ORIGIN);
+                let black = line
+                    .iter()
+                    .filter(|coords| lobby.black_tiles.contains(coords))
+                    .count();
+
+                Ok(format!(
+                    "Axial: {}\nCube: {}\nOffset: {}\nDistance to reference tile: {}\n\
+                     Black tiles on the way back: {}/{}",
+                    coords,
+                    coords.to_cube(),
+                    coords.to_offset(),
+                    coords.distance(Axial::ORIGIN),
+                    black,
+                    line.len()
+                ))
+            }
+            "animate" => {
+                let (mut animation, args) = Animation::from_args(args)?;
+                let (mut lobby, days) = self.lobby_from_args(&args)?;
+
+                animation.frame("Day 0", &render(&lobby.black_tiles))?;
+                for day in 1..=days {
+                    lobby.step();
+                    animation.frame(&format!("Day {}", day), &render(&lobby.black_tiles))?;
+                }
+
+                Ok(animation.finish())
+            }
+            "export" => {
+                let path = args.first().ok_or(anyhow!("Missing output file"))?;
+                let (mut lobby, days) = self.lobby_from_args(&args[1..])?;
+                lobby.run(days);
+
+                Image::from_grid(&brick_layout(&lobby.black_tiles), SCALE, |_, black| {
+                    if *black {
+                        Color(20, 20, 20)
+                    } else {
+                        Color(240, 240, 240)
+                    }
+                })
+                .save(path)?;
+
+                Ok(format!("Saved floor to {}", path))
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
+    }
 }
 
 fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
     let paths = input
         .lines()
-        .map(|line| {
-            let line = line?;
-            let mut chars = line.chars();
-
-            let mut dirs = Vec::new();
-            while let Some(c) = chars.next() {
-                let dir = match c {
-                    'w' => Direction::W,
-                    'e' => Direction::E,
-                    'n' => match chars.next().ok_or(anyhow!("Expected char after 'n'"))? {
-                        'w' => Direction::NW,
-                        'e' => Direction::NE,
-                        c => bail!("Unexpected '{}' after 'n'", c),
-                    },
-                    's' => match chars.next().ok_or(anyhow!("Expected char after 's'"))? {
-                        'w' => Direction::SW,
-                        'e' => Direction::SE,
-                        c => bail!("Unexpected '{}' after 's'", c),
-                    },
-                    c => bail!("Unexpected '{}'", c),
-                };
-                dirs.push(dir);
-            }
-
-            Ok(dirs)
-        })
+        .map(|line| Direction::parse_path(&line?))
         .collect::<Result<_>>()?;
 
     Ok(Box::new(Day24Solver(paths)))
@@ -1,9 +1,13 @@
 use crate::day::{Day, DynSolver, Solver};
+use crate::grid::{Cell, Coords, Grid};
+use crate::image::{Color, Image, ToColor};
 
+use std::collections::HashSet;
 use std::io::BufRead;
-use std::iter;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+
+const SCALE: usize = 4;
 
 pub const DAY3: Day = Day {
     title: "Toboggan Trajectory",
@@ -16,15 +20,38 @@ enum Tile {
     Empty,
 }
 
-struct Forest {
-    map: Vec<Tile>,
-    width: usize,
+impl Cell for Tile {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '#' => Some(Self::Tree),
+            '.' => Some(Self::Empty),
+            _ => None,
+        }
+    }
+
+    fn to_char(&self) -> char {
+        match self {
+            Self::Tree => '#',
+            Self::Empty => '.',
+        }
+    }
+}
+
+impl ToColor for Tile {
+    fn color(&self) -> Color {
+        match self {
+            Self::Tree => Color(30, 110, 40),
+            Self::Empty => Color(245, 245, 245),
+        }
+    }
 }
 
+// The map repeats infinitely to the right
+struct Forest(Grid<Tile>);
+
 impl Forest {
-    fn tile_at(&self, x: usize, y: usize) -> Option<Tile> {
-        let index = y * self.width + x;
-        self.map.get(index).copied()
+    fn wrap(&self, x: usize, y: usize) -> Coords {
+        (x % self.0.width(), y)
     }
 }
 
@@ -49,13 +76,14 @@ impl<'a> Toboggan<'a> {
 }
 
 impl Iterator for Toboggan<'_> {
-    type Item = Tile;
+    type Item = (Coords, Tile);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let tile = self.forest.tile_at(self.x % self.forest.width, self.y)?;
+        let coords = self.forest.wrap(self.x, self.y);
+        let tile = self.forest.0.get(coords).copied()?;
         self.x += self.right;
         self.y += self.down;
-        Some(tile)
+        Some((coords, tile))
     }
 }
 
@@ -64,7 +92,7 @@ impl Solver for Day3Solver {
     fn part1(&self) -> Result<String> {
         let forest = &self.0;
         let toboggan = Toboggan::new(forest, 3, 1);
-        let trees = toboggan.filter(|tile| *tile == Tile::Tree).count();
+        let trees = toboggan.filter(|(_, tile)| *tile == Tile::Tree).count();
 
         Ok(format!("Trees hit: {}", trees))
     }
@@ -80,21 +108,42 @@ impl Solver for Day3Solver {
         ];
         let trees: usize = sleds
             .iter_mut()
-            .map(|toboggan| toboggan.filter(|tile| *tile == Tile::Tree).count())
+            .map(|toboggan| toboggan.filter(|(_, tile)| *tile == Tile::Tree).count())
             .product();
 
         Ok(format!("Trees hit: {}", trees))
     }
+
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "export" => {
+                let path = args.first().ok_or(anyhow!("Missing output file"))?;
+                let right = args.get(1).map_or(Ok(3), |s| s.parse())?;
+                let down = args.get(2).map_or(Ok(1), |s| s.parse())?;
+                if down == 0 {
+                    bail!("The toboggan must move down");
+                }
+
+                let forest = &self.0;
+                let path_tiles = Toboggan::new(forest, right, down)
+                    .map(|(coords, _)| coords)
+                    .collect::<HashSet<_>>();
+                let image = Image::from_grid(&forest.0, SCALE, |coords, tile| {
+                    match (path_tiles.contains(&coords), tile) {
+                        (true, Tile::Tree) => Color(220, 30, 30),
+                        (true, Tile::Empty) => Color(250, 170, 170),
+                        (false, tile) => tile.color(),
+                    }
+                });
+                image.save(path)?;
+
+                Ok(format!("Saved forest to {}", path))
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
+    }
 }
 
 fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
-    let mut lines = input.lines().filter_map(|r| r.ok());
-    let first_line = lines.next().ok_or(anyhow!("Input is empty"))?;
-    let width = first_line.len();
-    let map = iter::once(first_line)
-        .chain(lines)
-        .flat_map(|line| line.chars().collect::<Vec<_>>().into_iter())
-        .map(|c| if c == '#' { Tile::Tree } else { Tile::Empty })
-        .collect();
-    Ok(Box::new(Day3Solver(Forest { map, width })))
+    Ok(Box::new(Day3Solver(Forest(Grid::parse(input)?))))
 }
@@ -1,8 +1,11 @@
 use crate::day::{Day, DynSolver, Solver};
 
 use std::collections::HashMap;
+use std::fmt;
+use std::fs;
 use std::io::BufRead;
 use std::iter;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 
 use anyhow::{anyhow, bail, Result};
@@ -13,106 +16,278 @@ pub const DAY4: Day = Day {
     solver_from_input,
 };
 
-enum HeightUnit {
-    In,
-    Cm,
+// The puzzle's rules, in the format of schema files: a field name, whether it's required or
+// optional, and the validator with its arguments
+const PUZZLE_SCHEMA: &str = "\
+byr required year 1920-2002
+iyr required year 2010-2020
+eyr required year 2020-2030
+hgt required measurement cm:150-193 in:59-76
+hcl required color
+ecl required one-of amb blu brn gry grn hzl oth
+pid required digits 9
+cid optional any";
+
+type Passport = HashMap<String, String>;
+
+enum Validator {
+    // Four digits, in the range
+    Year(RangeInclusive<u32>),
+    // A number followed by a unit, in the range for that unit
+    Measurement(Vec<(String, RangeInclusive<u32>)>),
+    // '#' followed by six hexadecimal digits
+    Color,
+    OneOf(Vec<String>),
+    Digits(usize),
+    Any,
+}
+
+enum FieldError {
+    Missing,
+    Malformed {
+        value: String,
+        expected: String,
+    },
+    OutOfRange {
+        value: u32,
+        range: RangeInclusive<u32>,
+    },
 }
 
-struct Height {
-    value: u32,
-    unit: HeightUnit,
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "missing"),
+            Self::Malformed { value, expected } => {
+                write!(f, "'{}' is not {}", value, expected)
+            }
+            Self::OutOfRange { value, range } => write!(
+                f,
+                "{} is not between {} and {}",
+                value,
+                range.start(),
+                range.end()
+            ),
+        }
+    }
 }
 
-impl FromStr for Height {
+impl Validator {
+    fn validate(&self, value: &str) -> Result<(), FieldError> {
+        let malformed = |expected: String| FieldError::Malformed {
+            value: value.to_string(),
+            expected,
+        };
+        let in_range = |number: u32, range: &RangeInclusive<u32>| {
+            if range.contains(&number) {
+                Ok(())
+            } else {
+                Err(FieldError::OutOfRange {
+                    value: number,
+                    range: range.clone(),
+                })
+            }
+        };
+        let is_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+
+        match self {
+            Self::Year(range) => {
+                if value.len() != 4 || !is_digits(value) {
+                    return Err(malformed("a four digit year".to_string()));
+                }
+                in_range(value.parse().unwrap(), range)
+            }
+            Self::Measurement(units) => {
+                let number_length = value.chars().take_while(|c| c.is_ascii_digit()).count();
+                let (number, unit) = value.split_at(number_length);
+                let range = units
+                    .iter()
+                    .find(|(name, _)| name == unit)
+                    .map(|(_, range)| range)
+                    .filter(|_| !number.is_empty())
+                    .ok_or_else(|| {
+                        let units = units.iter().map(|(name, _)| name).join(" or ");
+                        malformed(format!("a number followed by {}", units))
+                    })?;
+                let number = number
+                    .parse()
+                    .map_err(|_| malformed("a reasonably sized number".to_string()))?;
+                in_range(number, range)
+            }
+            Self::Color => {
+                let digits = value.strip_prefix('#').unwrap_or("");
+                let is_hex = |c: char| c.is_ascii_digit() || ('a'..='f').contains(&c);
+                if digits.len() != 6 || !digits.chars().all(is_hex) {
+                    return Err(malformed("a color like #12abef".to_string()));
+                }
+                Ok(())
+            }
+            Self::OneOf(options) => {
+                if !options.iter().any(|option| option == value) {
+                    return Err(malformed(format!("one of {}", options.join(", "))));
+                }
+                Ok(())
+            }
+            Self::Digits(length) => {
+                if value.len() != *length || !is_digits(value) {
+                    return Err(malformed(format!("a {} digit number", length)));
+                }
+                Ok(())
+            }
+            Self::Any => Ok(()),
+        }
+    }
+}
+
+struct FieldRule {
+    name: String,
+    required: bool,
+    validator: Validator,
+}
+
+impl FromStr for FieldRule {
     type Err = anyhow::Error;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let is_digit = |c: &char| *c >= '0' && *c <= '9';
-        let value = s.chars().take_while(is_digit).collect::<String>().parse()?;
-        let unit = match s.chars().skip_while(is_digit).collect::<String>().as_str() {
-            "in" => HeightUnit::In,
-            "cm" => HeightUnit::Cm,
-            _ => bail!("Invalid unit"),
+    // e.g. "hgt required measurement cm:150-193 in:59-76"
+    fn from_str(s: &str) -> Result<Self> {
+        let parse_range = |range: &str| -> Result<RangeInclusive<u32>> {
+            let (min, max) = range
+                .split('-')
+                .collect_tuple()
+                .ok_or(anyhow!("Invalid range '{}'", range))?;
+            Ok(min.parse()?..=max.parse()?)
+        };
+
+        let mut words = s.split_whitespace();
+        let mut next = |what: &str| words.next().ok_or(anyhow!("Missing {} in '{}'", what, s));
+        let name = next("field name")?.to_string();
+        let required = match next("required or optional")? {
+            "required" => true,
+            "optional" => false,
+            word => bail!("Expected required or optional, got '{}'", word),
+        };
+        let kind = next("validator")?;
+        let args = words.collect_vec();
+
+        let validator = match kind {
+            "year" => Validator::Year(parse_range(
+                args.first().ok_or(anyhow!("Missing year range"))?,
+            )?),
+            "measurement" => Validator::Measurement(
+                args.iter()
+                    .map(|arg| {
+                        let (unit, range) = arg
+                            .split(':')
+                            .collect_tuple()
+                            .ok_or(anyhow!("Invalid unit range '{}'", arg))?;
+                        Ok((unit.to_string(), parse_range(range)?))
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            "color" => Validator::Color,
+            "one-of" => Validator::OneOf(args.iter().map(|arg| arg.to_string()).collect()),
+            "digits" => Validator::Digits(
+                args.first()
+                    .ok_or(anyhow!("Missing amount of digits"))?
+                    .parse()?,
+            ),
+            "any" => Validator::Any,
+            kind => bail!("Unknown validator '{}'", kind),
         };
 
-        Ok(Height { value, unit })
+        Ok(Self {
+            name,
+            required,
+            validator,
+        })
     }
 }
 
-struct Day4Solver(Vec<HashMap<String, String>>);
+struct Schema(Vec<FieldRule>);
+
+impl Schema {
+    fn parse(s: &str) -> Result<Self> {
+        Ok(Self(
+            s.lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| line.parse())
+                .collect::<Result<_>>()?,
+        ))
+    }
+
+    // The errors of every field of the passport, only checking that required fields are present
+    // unless the values are validated too
+    fn errors<'a>(
+        &'a self,
+        passport: &'a Passport,
+        validate_values: bool,
+    ) -> impl Iterator<Item = (&'a str, FieldError)> + 'a {
+        self.0.iter().filter_map(move |rule| {
+            let error = match passport.get(&rule.name) {
+                None if rule.required => Some(FieldError::Missing),
+                None => None,
+                Some(_) if !validate_values => None,
+                Some(value) => rule.validator.validate(value).err(),
+            };
+            error.map(|error| (rule.name.as_str(), error))
+        })
+    }
+
+    fn count_valid(&self, passports: &[Passport], validate_values: bool) -> usize {
+        passports
+            .iter()
+            .filter(|passport| self.errors(passport, validate_values).next().is_none())
+            .count()
+    }
+}
+
+struct Day4Solver(Vec<Passport>);
 impl Solver for Day4Solver {
     fn part1(&self) -> Result<String> {
-        const REQUIRED_FIELDS: [&str; 7] = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"];
-        let valid_count = self
-            .0
-            .iter()
-            .filter(|passport| {
-                REQUIRED_FIELDS
-                    .iter()
-                    .all(|key| passport.contains_key(*key))
-            })
-            .count();
+        let valid_count = Schema::parse(PUZZLE_SCHEMA)?.count_valid(&self.0, false);
+
         Ok(format!("Valid passports: {}", valid_count))
     }
 
     fn part2(&self) -> Result<String> {
-        fn validate_passport(passport: &HashMap<String, String>) -> Option<()> {
-            let birth_year = passport.get("byr")?.parse::<u32>().ok()?;
-            let issue_year = passport.get("iyr")?.parse::<u32>().ok()?;
-            let expires_year = passport.get("eyr")?.parse::<u32>().ok()?;
-            let height = passport.get("hgt")?.parse::<Height>().ok()?;
-            let hair_color = passport.get("hcl")?;
-            let eye_color = passport.get("ecl")?;
-            let id = passport.get("pid")?;
-
-            if birth_year < 1920 || birth_year > 2002 {
-                return None;
-            }
-            if issue_year < 2010 || issue_year > 2020 {
-                return None;
-            }
-            if expires_year < 2020 || expires_year > 2030 {
-                return None;
-            }
-            match height.unit {
-                HeightUnit::In => {
-                    if height.value < 59 || height.value > 76 {
-                        return None;
-                    }
-                }
-                HeightUnit::Cm => {
-                    if height.value < 150 || height.value > 193 {
-                        return None;
-                    }
-                }
-            }
-            if hair_color.chars().next()? != '#' {
-                return None;
-            }
-            if hair_color
-                .chars()
-                .skip(1)
-                .any(|c| (c < '0' || c > '9') && (c < 'a' || c > 'f'))
-            {
-                return None;
-            }
-            if !["amb", "blu", "brn", "gry", "grn", "hzl", "oth"].contains(&eye_color.as_str()) {
-                return None;
-            }
-            if id.len() != 9 || id.chars().any(|c| c < '0' || c > '9') {
-                return None;
-            }
+        let valid_count = Schema::parse(PUZZLE_SCHEMA)?.count_valid(&self.0, true);
 
-            Some(())
-        }
+        Ok(format!("Valid passports: {}", valid_count))
+    }
 
-        let valid_count = self
-            .0
-            .iter()
-            .filter(|passport| validate_passport(&passport).is_some())
-            .count();
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "report" => {
+                let schema = match args.first() {
+                    Some(path) => Schema::parse(&fs::read_to_string(path)?)?,
+                    None => Schema::parse(PUZZLE_SCHEMA)?,
+                };
 
-        Ok(format!("Valid passports: {}", valid_count))
+                let passports = self
+                    .0
+                    .iter()
+                    .enumerate()
+                    .map(|(i, passport)| {
+                        let errors = schema
+                            .errors(passport, true)
+                            .map(|(field, error)| format!("{}: {}", field, error))
+                            .join("; ");
+                        if errors.is_empty() {
+                            format!("Passport {}: valid", i + 1)
+                        } else {
+                            format!("Passport {}: {}", i + 1, errors)
+                        }
+                    })
+                    .join("\n");
+
+                Ok(format!(
+                    "{}\nValid passports: {}",
+                    passports,
+                    schema.count_valid(&self.0, true)
+                ))
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
     }
 }
 
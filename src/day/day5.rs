@@ -1,5 +1,7 @@
 use crate::day::{Day, DynSolver, Solver};
+use crate::grid::{Cell, Grid};
 
+use std::collections::HashSet;
 use std::io::BufRead;
 
 use anyhow::{anyhow, bail, Result};
@@ -10,62 +12,326 @@ pub const DAY5: Day = Day {
     solver_from_input,
 };
 
+#[derive(Clone, Copy, PartialEq)]
 struct Seat {
     row: u32,
     column: u32,
 }
 
-impl Seat {
-    fn id(&self) -> u32 {
-        self.row * 8 + self.column
+// The dimensions of the plane, as the number of characters used to encode the row and column
+#[derive(Clone, Copy, PartialEq)]
+struct Plane {
+    row_bits: u32,
+    column_bits: u32,
+}
+
+impl Plane {
+    // So that every seat ID, and the number of seats, fits in a u32
+    const MAX_BITS: usize = 31;
+
+    fn from_bits(row_bits: usize, column_bits: usize) -> Result<Self> {
+        if row_bits + column_bits > Self::MAX_BITS {
+            bail!(
+                "Planes with more than 2^{} seats are not supported",
+                Self::MAX_BITS
+            );
+        }
+
+        Ok(Self {
+            row_bits: row_bits as u32,
+            column_bits: column_bits as u32,
+        })
+    }
+
+    fn new(rows: u32, columns: u32) -> Result<Self> {
+        if !rows.is_power_of_two() || !columns.is_power_of_two() {
+            bail!(
+                "Rows and columns must be powers of two, got {}x{}",
+                rows,
+                columns
+            );
+        }
+
+        Self::from_bits(
+            rows.trailing_zeros() as usize,
+            columns.trailing_zeros() as usize,
+        )
+    }
+
+    // The plane a boarding pass is for, from how many characters encode the row
+    fn of_code(code: &str) -> Result<Self> {
+        let row_bits = code.chars().filter(|c| *c == 'F' || *c == 'B').count();
+        Self::from_bits(row_bits, code.chars().count() - row_bits)
+    }
+
+    fn rows(self) -> u32 {
+        1 << self.row_bits
+    }
+
+    fn columns(self) -> u32 {
+        1 << self.column_bits
+    }
+
+    fn id(self, seat: Seat) -> u32 {
+        seat.row * self.columns() + seat.column
+    }
+
+    fn seat(self, id: u32) -> Result<Seat> {
+        if id >= self.rows() * self.columns() {
+            bail!("No seat with ID {} on the plane", id);
+        }
+
+        Ok(Seat {
+            row: id / self.columns(),
+            column: id % self.columns(),
+        })
+    }
+
+    // e.g. "FBFBBFFRLR" for row 44, column 5
+    fn decode(self, code: &str) -> Result<Seat> {
+        let chars = code.chars().collect_vec();
+        if chars.len() != (self.row_bits + self.column_bits) as usize {
+            bail!(
+                "Boarding pass '{}' should have {} characters",
+                code,
+                self.row_bits + self.column_bits
+            );
+        }
+
+        let (row_chars, column_chars) = chars.split_at(self.row_bits as usize);
+        let decode_bits = |chars: &[char], zero, one| {
+            chars.iter().try_fold(0, |n, c| match *c {
+                c if c == zero => Ok(n << 1),
+                c if c == one => Ok(n << 1 | 1),
+                c => bail!(
+                    "Invalid character '{}' in boarding pass '{}', expected {} or {}",
+                    c,
+                    code,
+                    zero,
+                    one
+                ),
+            })
+        };
+
+        Ok(Seat {
+            row: decode_bits(row_chars, 'F', 'B')?,
+            column: decode_bits(column_chars, 'L', 'R')?,
+        })
+    }
+
+    fn encode(self, seat: Seat) -> Result<String> {
+        if seat.row >= self.rows() || seat.column >= self.columns() {
+            bail!(
+                "No seat at row {}, column {} on a plane with {} rows and {} columns",
+                seat.row,
+                seat.column,
+                self.rows(),
+                self.columns()
+            );
+        }
+
+        let encode_bits = |n: u32, bits: u32, zero, one| {
+            (0..bits)
+                .rev()
+                .map(move |bit| if n >> bit & 1 == 1 { one } else { zero })
+        };
+        Ok(encode_bits(seat.row, self.row_bits, 'F', 'B')
+            .chain(encode_bits(seat.column, self.column_bits, 'L', 'R'))
+            .collect())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SeatState {
+    Taken,
+    Free,
+    // Seats at the very front and back that don't exist on this aircraft
+    Missing,
+    Mine,
+}
+
+impl Cell for SeatState {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '#' => Some(Self::Taken),
+            '.' => Some(Self::Free),
+            ' ' => Some(Self::Missing),
+            'X' => Some(Self::Mine),
+            _ => None,
+        }
+    }
+
+    fn to_char(&self) -> char {
+        match self {
+            Self::Taken => '#',
+            Self::Free => '.',
+            Self::Missing => ' ',
+            Self::Mine => 'X',
+        }
     }
 }
 
-struct Day5Solver(Vec<Seat>);
+struct Day5Solver {
+    plane: Plane,
+    seats: Vec<Seat>,
+}
 impl Solver for Day5Solver {
     fn part1(&self) -> Result<String> {
         let highest_id = self
-            .0
+            .seats
             .iter()
-            .map(Seat::id)
+            .map(|seat| self.plane.id(*seat))
             .max()
             .ok_or(anyhow!("No passports"))?;
         Ok(format!("Highest seat ID: {}", highest_id))
     }
 
     fn part2(&self) -> Result<String> {
-        let sorted_seats = self.0.iter().map(Seat::id).sorted();
-        for (a, b) in sorted_seats.tuple_windows() {
-            if a != b - 1 {
-                return Ok(format!("Your seat ID: {}", a + 1));
+        let id = self.my_seat_id()?;
+
+        Ok(format!("Your seat ID: {}", id))
+    }
+
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "decode" => {
+                let code = args.first().ok_or(anyhow!("Missing boarding pass"))?;
+                let plane = Plane::of_code(code)?;
+                let seat = plane.decode(code)?;
+
+                Ok(format!(
+                    "Row {}, column {}, seat ID {}",
+                    seat.row,
+                    seat.column,
+                    plane.id(seat)
+                ))
             }
-        }
+            "encode" => {
+                let (row, column) = args
+                    .iter()
+                    .take(2)
+                    .map(|s| s.parse::<u32>())
+                    .collect_tuple()
+                    .ok_or(anyhow!("Missing row and column"))?;
+                let plane = match (args.get(2), args.get(3)) {
+                    (Some(rows), Some(columns)) => Plane::new(rows.parse()?, columns.parse()?)?,
+                    _ => self.plane,
+                };
+                let seat = Seat {
+                    row: row?,
+                    column: column?,
+                };
+
+                Ok(format!(
+                    "Boarding pass {}, seat ID {}",
+                    plane.encode(seat)?,
+                    plane.id(seat)
+                ))
+            }
+            "seat" => {
+                let id = args.first().ok_or(anyhow!("Missing seat ID"))?.parse()?;
+                let seat = self.plane.seat(id)?;
 
-        bail!("No seat ID found")
+                Ok(format!(
+                    "Row {}, column {}, boarding pass {}",
+                    seat.row,
+                    seat.column,
+                    self.plane.encode(seat)?
+                ))
+            }
+            "map" => {
+                let map = self.seat_map()?;
+
+                Ok((0..map.height())
+                    .map(|row| {
+                        let seats = map.row(row).iter().map(Cell::to_char).collect::<String>();
+                        format!("{:>4} {}", row, seats)
+                    })
+                    .join("\n"))
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
     }
 }
 
-fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
-    let seats = input
-        .lines()
-        .map(|line| -> Result<Seat> {
-            let line = line?;
-            let mut row = 0;
-            for (i, c) in line[..7].chars().enumerate() {
-                if c == 'B' {
-                    row |= 1 << (6 - i);
-                }
-            }
-            let mut column = 0;
-            for (i, c) in line[7..].chars().enumerate() {
-                if c == 'R' {
-                    column |= 1 << (2 - i);
+impl Day5Solver {
+    // Every free seat between the taken ones, where the seats on both sides by ID are taken
+    fn my_seat_candidates(&self) -> Vec<u32> {
+        let taken = self
+            .seats
+            .iter()
+            .map(|seat| self.plane.id(*seat))
+            .collect::<HashSet<_>>();
+        let (first, last) = match taken.iter().minmax().into_option() {
+            Some((first, last)) => (*first, *last),
+            None => return Vec::new(),
+        };
+
+        (first + 1..last)
+            .filter(|id| {
+                !taken.contains(id) && taken.contains(&(id - 1)) && taken.contains(&(id + 1))
+            })
+            .collect()
+    }
+
+    fn my_seat_id(&self) -> Result<u32> {
+        self.my_seat_candidates()
+            .into_iter()
+            .exactly_one()
+            .map_err(|candidates| match candidates.len() {
+                0 => anyhow!("No seat ID found"),
+                _ => anyhow!(
+                    "Multiple seats could be yours: {}",
+                    candidates.map(|id| id.to_string()).join(", ")
+                ),
+            })
+    }
+
+    fn seat_map(&self) -> Result<Grid<SeatState>> {
+        let ids = self
+            .seats
+            .iter()
+            .map(|seat| self.plane.id(*seat))
+            .collect::<HashSet<_>>();
+        let (first, last) = ids
+            .iter()
+            .copied()
+            .minmax()
+            .into_option()
+            .ok_or(anyhow!("No passports"))?;
+        let mine = self.my_seat_id().ok();
+
+        Ok(Grid::from_fn(
+            self.plane.columns() as usize,
+            self.plane.rows() as usize,
+            |(column, row)| {
+                let id = self.plane.id(Seat {
+                    row: row as u32,
+                    column: column as u32,
+                });
+                if ids.contains(&id) {
+                    SeatState::Taken
+                } else if Some(id) == mine {
+                    SeatState::Mine
+                } else if id > first && id < last {
+                    SeatState::Free
+                } else {
+                    SeatState::Missing
                 }
-            }
+            },
+        ))
+    }
+}
 
-            Ok(Seat { row, column })
-        })
+fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
+    let codes = input.lines().collect::<Result<Vec<_>, _>>()?;
+    let plane = codes
+        .first()
+        .map_or(Plane::new(128, 8), |code| Plane::of_code(code))?;
+    let seats = codes
+        .iter()
+        .map(|code| plane.decode(code))
         .collect::<Result<_>>()?;
 
-    Ok(Box::new(Day5Solver(seats)))
+    Ok(Box::new(Day5Solver { plane, seats }))
 }
@@ -1,9 +1,11 @@
 use crate::day::{Day, DynSolver, Solver};
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fs;
 use std::io::BufRead;
+use std::iter;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use itertools::Itertools;
 
 const SHINY_GOLD: &str = "shiny gold";
@@ -18,86 +20,266 @@ struct BagSpec {
     contents: Vec<(u32, String)>,
 }
 
-struct Day7Solver(HashMap<String, BagSpec>);
-impl Solver for Day7Solver {
-    fn part1(&self) -> Result<String> {
-        fn walk<'a>(
-            root: &'a str,
-            specs: &'a HashMap<String, BagSpec>,
-            visited: &mut HashMap<&'a str, bool>,
-        ) -> bool {
-            if let Some(contains) = visited.get(root) {
-                return *contains;
-            }
+struct BagGraph {
+    specs: HashMap<String, BagSpec>,
+    // The bags directly containing every bag
+    containers: HashMap<String, Vec<String>>,
+}
 
-            let spec = &specs[root];
+impl BagGraph {
+    fn new(rules: Vec<(String, BagSpec)>) -> Result<Self> {
+        let mut specs = HashMap::new();
+        for (color, spec) in rules {
+            if specs.contains_key(&color) {
+                bail!("Duplicate rule for '{}'", color);
+            }
+            specs.insert(color, spec);
+        }
 
+        let mut containers: HashMap<String, Vec<String>> = HashMap::new();
+        for (color, spec) in &specs {
             for (_, child) in &spec.contents {
-                if child == SHINY_GOLD {
-                    visited.insert(root, true);
-                    return true;
+                if !specs.contains_key(child) {
+                    bail!("'{}' contains '{}', which has no rule", color, child);
                 }
+                containers
+                    .entry(child.clone())
+                    .or_default()
+                    .push(color.clone());
+            }
+        }
 
-                if walk(child, specs, visited) {
-                    visited.insert(root, true);
-                    return true;
+        Ok(Self { specs, containers })
+    }
+
+    fn spec(&self, color: &str) -> Result<&BagSpec> {
+        self.specs
+            .get(color)
+            .ok_or(anyhow!("Unknown bag color '{}'", color))
+    }
+
+    // Every bag that eventually contains the given one
+    fn ancestors(&self, color: &str) -> Result<BTreeSet<&str>> {
+        self.spec(color)?;
+
+        let mut ancestors = BTreeSet::new();
+        let mut queue = VecDeque::from(vec![color]);
+        while let Some(color) = queue.pop_front() {
+            for container in self.containers.get(color).into_iter().flatten() {
+                if ancestors.insert(container.as_str()) {
+                    queue.push_back(container);
                 }
             }
+        }
+
+        Ok(ancestors)
+    }
 
-            visited.insert(root, false);
-            false
+    // Every bag eventually contained in the given one
+    fn descendants(&self, color: &str) -> Result<BTreeSet<&str>> {
+        let mut descendants = BTreeSet::new();
+        let mut queue = VecDeque::from(vec![self.spec(color)?]);
+        while let Some(spec) = queue.pop_front() {
+            for (_, child) in &spec.contents {
+                if descendants.insert(child.as_str()) {
+                    queue.push_back(&self.specs[child]);
+                }
+            }
         }
 
-        let mut visited = HashMap::new();
-        for k in self.0.keys() {
-            walk(k, &self.0, &mut visited);
+        Ok(descendants)
+    }
+
+    // How many bags of every color the given bag holds in total, directly or inside other bags
+    fn total_contents(&self, color: &str) -> Result<BTreeMap<&str, u64>> {
+        let (color, _) = self
+            .specs
+            .get_key_value(color)
+            .ok_or(anyhow!("Unknown bag color '{}'", color))?;
+        let order = self.sorted_from(&[color])?;
+
+        let mut counts = BTreeMap::new();
+        counts.insert(order[0], 1u64);
+        for bag in &order {
+            let count = counts[bag];
+            for (amount, child) in &self.specs[*bag].contents {
+                let total = counts.entry(child.as_str()).or_insert(0);
+                *total = count
+                    .checked_mul(*amount as u64)
+                    .and_then(|added| total.checked_add(added))
+                    .ok_or(anyhow!("Too many {} bags inside {} to count", child, color))?;
+            }
         }
+        counts.remove(color.as_str());
 
-        let usable_colors_count = visited
+        Ok(counts)
+    }
+
+    // How many bags the given bag holds in total, of any color
+    fn total_bags(&self, color: &str) -> Result<u64> {
+        self.total_contents(color)?
             .values()
-            .filter(|contains_shiny| **contains_shiny)
-            .count();
+            .try_fold(0u64, |sum, count| sum.checked_add(*count))
+            .ok_or(anyhow!("Too many bags inside {} to count", color))
+    }
 
-        Ok(format!(
-            "Amount of usable bag colors: {}",
-            usable_colors_count
-        ))
+    // Every bag, with containers before the bags they contain
+    fn topological_order(&self) -> Result<Vec<&str>> {
+        let colors = self
+            .specs
+            .keys()
+            .map(|color| color.as_str())
+            .sorted()
+            .collect_vec();
+        self.sorted_from(&colors)
     }
 
-    fn part2(&self) -> Result<String> {
-        fn walk<'a>(
-            root: &'a str,
-            specs: &'a HashMap<String, BagSpec>,
-            counted: &mut HashMap<&'a str, u32>,
-        ) -> u32 {
-            if let Some(count) = counted.get(root) {
-                return *count;
+    // The bags reachable from the roots, with containers before the bags they contain, or an
+    // error describing a cycle among them
+    fn sorted_from<'a>(&'a self, roots: &[&'a str]) -> Result<Vec<&'a str>> {
+        fn visit<'a>(
+            color: &'a str,
+            graph: &'a BagGraph,
+            path: &mut Vec<&'a str>,
+            done: &mut HashSet<&'a str>,
+            order: &mut Vec<&'a str>,
+        ) -> Result<()> {
+            if done.contains(color) {
+                return Ok(());
+            }
+            if let Some(start) = path.iter().position(|bag| *bag == color) {
+                bail!(
+                    "Bags contain themselves: {} -> {}",
+                    path[start..].iter().join(" -> "),
+                    color
+                );
             }
 
-            let spec = &specs[root];
+            path.push(color);
+            for (_, child) in &graph.spec(color)?.contents {
+                visit(child, graph, path, done, order)?;
+            }
+            path.pop();
 
-            let bag_count = spec
-                .contents
-                .iter()
-                .map(|(amount, child)| amount + amount * walk(child, specs, counted))
-                .sum();
-            counted.insert(root, bag_count);
+            done.insert(color);
+            order.push(color);
+            Ok(())
+        }
 
-            bag_count
+        let mut done = HashSet::new();
+        let mut order = Vec::new();
+        for root in roots {
+            visit(root, self, &mut Vec::new(), &mut done, &mut order)?;
         }
+        order.reverse();
+
+        Ok(order)
+    }
+
+    // The graph in Graphviz DOT format, with edges from containers to the bags they contain
+    fn dot(&self, highlight: Option<&str>) -> String {
+        let nodes = self.specs.keys().sorted().map(|color| {
+            if Some(color.as_str()) == highlight {
+                format!("    \"{}\" [style=filled, fillcolor=gold];", color)
+            } else {
+                format!("    \"{}\";", color)
+            }
+        });
+        let edges = self
+            .specs
+            .iter()
+            .sorted_by_key(|(color, _)| *color)
+            .flat_map(|(color, spec)| {
+                spec.contents.iter().map(move |(amount, child)| {
+                    format!("    \"{}\" -> \"{}\" [label={}];", color, child, amount)
+                })
+            });
+
+        iter::once("digraph bags {".to_string())
+            .chain(nodes)
+            .chain(edges)
+            .chain(iter::once("}".to_string()))
+            .join("\n")
+    }
+}
 
-        let mut counted = HashMap::new();
-        let shiny_gold_content_count = walk(SHINY_GOLD, &self.0, &mut counted);
+struct Day7Solver(BagGraph);
+impl Solver for Day7Solver {
+    fn part1(&self) -> Result<String> {
+        let usable_colors_count = self.0.ancestors(SHINY_GOLD)?.len();
+
+        Ok(format!(
+            "Amount of usable bag colors: {}",
+            usable_colors_count
+        ))
+    }
+
+    fn part2(&self) -> Result<String> {
+        let shiny_gold_content_count = self.0.total_bags(SHINY_GOLD)?;
 
         Ok(format!(
             "Amount of bags contained in my bag: {}",
             shiny_gold_content_count
         ))
     }
+
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        let color = || -> Result<String> {
+            if args.is_empty() {
+                bail!("Missing bag color");
+            }
+            Ok(args.join(" "))
+        };
+
+        match name {
+            "containers" => {
+                let color = color()?;
+                let ancestors = self.0.ancestors(&color)?;
+
+                Ok(format!(
+                    "{} bag colors eventually contain {}:\n{}",
+                    ancestors.len(),
+                    color,
+                    ancestors.iter().join("\n")
+                ))
+            }
+            "contents" => {
+                let color = color()?;
+                let reachable = self.0.descendants(&color)?;
+                let contents = self.0.total_contents(&color)?;
+
+                Ok(format!(
+                    "{} contains {} bags of {} colors:\n{}",
+                    color,
+                    self.0.total_bags(&color)?,
+                    reachable.len(),
+                    contents
+                        .iter()
+                        .map(|(color, count)| format!("{} {}", count, color))
+                        .join("\n")
+                ))
+            }
+            "order" => Ok(self.0.topological_order()?.iter().join("\n")),
+            "dot" => {
+                let path = args.first().ok_or(anyhow!("Missing output file"))?;
+                let highlight = args[1..].join(" ");
+                let highlight = if highlight.is_empty() {
+                    None
+                } else {
+                    self.0.spec(&highlight)?;
+                    Some(highlight.as_str())
+                };
+
+                fs::write(path, self.0.dot(highlight))?;
+                Ok(format!("Saved graph to {}", path))
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
+    }
 }
 
 fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
-    let specs = input
+    let rules = input
         .lines()
         .map(|line| -> Result<(String, BagSpec)> {
             let line = line?;
@@ -110,7 +292,7 @@ fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
         })
         .collect::<Result<_>>()?;
 
-    Ok(Box::new(Day7Solver(specs)))
+    Ok(Box::new(Day7Solver(BagGraph::new(rules)?)))
 }
 
 fn parse_contents<'a>(mut words: impl Iterator<Item = &'a str>) -> Option<Vec<(u32, String)>> {
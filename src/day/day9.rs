@@ -1,8 +1,10 @@
 use crate::day::{Day, DynSolver, Solver};
+use crate::sums::{invalid_positions, subarray_with_sum};
 
 use std::io::BufRead;
+use std::ops::Range;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use itertools::Itertools;
 
 const PREAMBLE_LENGTH: usize = 25;
@@ -15,45 +17,69 @@ pub const DAY9: Day = Day {
 struct Day9Solver(Vec<u64>);
 impl Solver for Day9Solver {
     fn part1(&self) -> Result<String> {
-        let invalid_number = first_invalid_number(&self.0);
-        Ok(format!(
-            "First invalid number: {}",
-            invalid_number.ok_or(anyhow!("No solution"))?
-        ))
+        let invalid_number = self.first_invalid_number(PREAMBLE_LENGTH)?;
+
+        Ok(format!("First invalid number: {}", invalid_number))
     }
 
     fn part2(&self) -> Result<String> {
-        let numbers = &self.0;
-        let invalid_number = first_invalid_number(numbers).ok_or(anyhow!("No invalid number"))?;
-
-        let weakness = (0..numbers.len())
-            .flat_map(|start| ((start + 2)..numbers.len()).map(move |end| &numbers[start..end]))
-            .filter(|range| range.iter().sum::<u64>() == invalid_number)
-            .flat_map(|range| range.iter().minmax().into_option().into_iter())
-            .map(|(min, max)| min + max)
-            .next()
-            .ok_or(anyhow!("No weakness"))?;
+        let (_, weakness) = self.weakness(PREAMBLE_LENGTH)?;
 
         Ok(format!("Weakness: {}", weakness))
     }
+
+    fn command(&self, name: &str, args: &[String]) -> Result<String> {
+        match name {
+            "invalid" => {
+                let preamble_length = args.first().map_or(Ok(PREAMBLE_LENGTH), |s| s.parse())?;
+                let invalid = invalid_positions(&self.0, preamble_length);
+                if invalid.is_empty() {
+                    bail!(
+                        "Every number after the preamble of {} is valid",
+                        preamble_length
+                    );
+                }
+
+                Ok(invalid
+                    .iter()
+                    .map(|i| format!("Line {}: {}", i + 1, self.0[*i]))
+                    .join("\n"))
+            }
+            "weakness" => {
+                let preamble_length = args.first().map_or(Ok(PREAMBLE_LENGTH), |s| s.parse())?;
+                let (range, weakness) = self.weakness(preamble_length)?;
+
+                Ok(format!(
+                    "Lines {} to {} add up to the first invalid number: {}\nWeakness: {}",
+                    range.start + 1,
+                    range.end,
+                    self.0[range.clone()].iter().join(" + "),
+                    weakness
+                ))
+            }
+            _ => bail!("Unknown command '{}'", name),
+        }
+    }
 }
 
-fn first_invalid_number(numbers: &[u64]) -> Option<u64> {
-    Some(
-        (0..)
-            .map(|start| {
-                let end = start + PREAMBLE_LENGTH;
-                (
-                    numbers[end],
-                    (start..end)
-                        .cartesian_product(start..end)
-                        .filter(|(a, b)| a != b)
-                        .any(|(a, b)| numbers[a] + numbers[b] == numbers[end]),
-                )
-            })
-            .find(|(_, valid)| !*valid)?
-            .0,
-    )
+impl Day9Solver {
+    fn first_invalid_number(&self, preamble_length: usize) -> Result<u64> {
+        let position = *invalid_positions(&self.0, preamble_length)
+            .first()
+            .ok_or(anyhow!("No invalid number"))?;
+
+        Ok(self.0[position])
+    }
+
+    // The range of at least two numbers adding up to the first invalid number, along with the sum
+    // of its smallest and largest numbers
+    fn weakness(&self, preamble_length: usize) -> Result<(Range<usize>, u64)> {
+        let invalid_number = self.first_invalid_number(preamble_length)?;
+        let range = subarray_with_sum(&self.0, invalid_number, 2).ok_or(anyhow!("No weakness"))?;
+        let (min, max) = self.0[range.clone()].iter().minmax().into_option().unwrap();
+
+        Ok((range, min + max))
+    }
 }
 
 fn solver_from_input(input: &mut dyn BufRead) -> Result<DynSolver> {
@@ -0,0 +1,207 @@
+use std::fmt;
+use std::io::BufRead;
+use std::iter;
+use std::ops::Index;
+
+use anyhow::{anyhow, bail, Result};
+
+pub type Coords = (usize, usize);
+
+// Offsets as (x, y), with y growing downwards
+pub const ORTHOGONAL: [(isize, isize); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+pub const ALL_DIRECTIONS: [(isize, isize); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+pub trait Cell: Sized {
+    fn from_char(c: char) -> Option<Self>;
+    fn to_char(&self) -> char;
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn from_fn(width: usize, height: usize, f: impl FnMut(Coords) -> T) -> Self {
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(f)
+            .collect();
+
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn position(&self, (x, y): Coords) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, coords: Coords) -> Option<&T> {
+        self.cells.get(self.position(coords)?)
+    }
+
+    pub fn get_mut(&mut self, coords: Coords) -> Option<&mut T> {
+        let index = self.position(coords)?;
+        self.cells.get_mut(index)
+    }
+
+    pub fn coords(&self) -> impl Iterator<Item = Coords> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.cells.iter()
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (Coords, &T)> {
+        self.coords().zip(self.cells.iter())
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.cells[(y * self.width)..((y + 1) * self.width)]
+    }
+
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        self.cells.iter().skip(x).step_by(self.width)
+    }
+
+    pub fn offset(&self, (x, y): Coords, (dx, dy): (isize, isize)) -> Option<Coords> {
+        let x = (x as isize).checked_add(dx)?;
+        let y = (y as isize).checked_add(dy)?;
+        if x < 0 || y < 0 {
+            return None;
+        }
+
+        let coords = (x as usize, y as usize);
+        self.position(coords).map(|_| coords)
+    }
+
+    pub fn neighbors<'a>(
+        &'a self,
+        coords: Coords,
+        directions: &'a [(isize, isize)],
+    ) -> impl Iterator<Item = Coords> + 'a {
+        directions
+            .iter()
+            .filter_map(move |direction| self.offset(coords, *direction))
+    }
+
+    // Every coordinate in the given direction, until the edge of the grid
+    pub fn ray(
+        &self,
+        coords: Coords,
+        direction: (isize, isize),
+    ) -> impl Iterator<Item = Coords> + '_ {
+        iter::successors(Some(coords), move |coords| self.offset(*coords, direction)).skip(1)
+    }
+}
+
+impl<T> Index<Coords> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, coords: Coords) -> &T {
+        self.get(coords).expect("Coordinates out of bounds")
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn rotate_right(&self) -> Self {
+        Self::from_fn(self.height, self.width, |(x, y)| {
+            self.cells[(self.height - 1 - x) * self.width + y].clone()
+        })
+    }
+
+    pub fn flip_horizontal(&self) -> Self {
+        Self::from_fn(self.width, self.height, |(x, y)| {
+            self.cells[y * self.width + (self.width - 1 - x)].clone()
+        })
+    }
+
+    // All 8 rotations and reflections of the grid
+    pub fn orientations(&self) -> impl Iterator<Item = Self> {
+        let rotations =
+            |grid: Self| iter::successors(Some(grid), |grid| Some(grid.rotate_right())).take(4);
+        rotations(self.clone()).chain(rotations(self.flip_horizontal()))
+    }
+}
+
+impl<T: Cell> Grid<T> {
+    pub fn parse_lines(lines: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self> {
+        let mut width = None;
+        let mut height = 0;
+        let mut cells = Vec::new();
+        for line in lines {
+            let line = line.as_ref();
+            let row = line
+                .chars()
+                .map(|c| T::from_char(c).ok_or(anyhow!("Invalid character '{}'", c)))
+                .collect::<Result<Vec<_>>>()?;
+            if row.is_empty() {
+                bail!("Row {} is empty", height);
+            }
+
+            match width {
+                None => width = Some(row.len()),
+                Some(width) if width != row.len() => {
+                    bail!("Row {} has width {}, expected {}", height, row.len(), width)
+                }
+                _ => {}
+            }
+            cells.extend(row);
+            height += 1;
+        }
+
+        Ok(Self {
+            width: width.ok_or(anyhow!("Grid is empty"))?,
+            height,
+            cells,
+        })
+    }
+
+    pub fn parse(input: &mut dyn BufRead) -> Result<Self> {
+        let lines = input.lines().collect::<Result<Vec<_>, _>>()?;
+        Self::parse_lines(lines)
+    }
+}
+
+impl<T: Cell> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.height {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            for cell in self.row(y) {
+                write!(f, "{}", cell.to_char())?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,247 @@
+use std::fmt;
+use std::ops::{Add, Sub};
+
+use anyhow::{anyhow, bail, Result};
+
+// Pointy-topped hexagons, where every row of tiles runs from east to west
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    E,
+    NE,
+    NW,
+    W,
+    SW,
+    SE,
+}
+
+// Counter-clockwise, starting from the east
+pub const DIRECTIONS: [Direction; 6] = [
+    Direction::E,
+    Direction::NE,
+    Direction::NW,
+    Direction::W,
+    Direction::SW,
+    Direction::SE,
+];
+
+impl Direction {
+    fn offset(self) -> Axial {
+        let (q, r) = match self {
+            Self::E => (1, 0),
+            Self::NE => (1, -1),
+            Self::NW => (0, -1),
+            Self::W => (-1, 0),
+            Self::SW => (-1, 1),
+            Self::SE => (0, 1),
+        };
+        Axial { q, r }
+    }
+
+    // Parses directions written without delimiters, e.g. "esenee"
+    pub fn parse_path(s: &str) -> Result<Vec<Self>> {
+        let mut chars = s.chars();
+
+        let mut path = Vec::new();
+        while let Some(c) = chars.next() {
+            let direction = match c {
+                'w' => Self::W,
+                'e' => Self::E,
+                'n' => match chars.next().ok_or(anyhow!("Expected char after 'n'"))? {
+                    'w' => Self::NW,
+                    'e' => Self::NE,
+                    c => bail!("Unexpected '{}' after 'n'", c),
+                },
+                's' => match chars.next().ok_or(anyhow!("Expected char after 's'"))? {
+                    'w' => Self::SW,
+                    'e' => Self::SE,
+                    c => bail!("Unexpected '{}' after 's'", c),
+                },
+                c => bail!("Unexpected '{}'", c),
+            };
+            path.push(direction);
+        }
+
+        Ok(path)
+    }
+}
+
+// Axial coordinates, with q growing to the east and r to the south-east
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Axial {
+    pub q: i32,
+    pub r: i32,
+}
+
+// Cube coordinates, where x + y + z = 0
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cube {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+// Offset coordinates, where odd rows are shifted half a tile to the east
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Offset {
+    pub column: i32,
+    pub row: i32,
+}
+
+impl Axial {
+    pub const ORIGIN: Self = Self { q: 0, r: 0 };
+
+    pub fn neighbor(self, direction: Direction) -> Self {
+        self + direction.offset()
+    }
+
+    pub fn neighbors(self) -> impl Iterator<Item = Self> {
+        DIRECTIONS
+            .iter()
+            .map(move |direction| self.neighbor(*direction))
+    }
+
+    pub fn walk(self, path: &[Direction]) -> Self {
+        path.iter()
+            .fold(self, |coords, direction| coords.neighbor(*direction))
+    }
+
+    pub fn distance(self, other: Self) -> u32 {
+        let Cube { x, y, z } = (self - other).to_cube();
+        ((x.abs() + y.abs() + z.abs()) / 2) as u32
+    }
+
+    // Every tile at exactly the given distance, counter-clockwise from the south-west corner
+    pub fn ring(self, radius: u32) -> Vec<Self> {
+        if radius == 0 {
+            return vec![self];
+        }
+
+        let corner = (0..radius).fold(self, |coords, _| coords.neighbor(Direction::SW));
+        DIRECTIONS
+            .iter()
+            .flat_map(|direction| (0..radius).map(move |_| *direction))
+            .scan(corner, |coords, direction| {
+                let current = *coords;
+                *coords = coords.neighbor(direction);
+                Some(current)
+            })
+            .collect()
+    }
+
+    // The tiles crossed by a straight line between the centers of two tiles, including both ends
+    pub fn line(self, to: Self) -> Vec<Self> {
+        let distance = self.distance(to);
+        if distance == 0 {
+            return vec![self];
+        }
+
+        let from = self.to_cube();
+        let to = to.to_cube();
+        let lerp = |a: i32, b: i32, t: f64| a as f64 + (b - a) as f64 * t;
+        (0..=distance)
+            .map(|i| {
+                let t = i as f64 / distance as f64;
+                // Nudged slightly, so that points on the edge between two tiles consistently
+                // go to the same one
+                Cube::round(
+                    lerp(from.x, to.x, t) + 1e-6,
+                    lerp(from.y, to.y, t) + 2e-6,
+                    lerp(from.z, to.z, t) - 3e-6,
+                )
+                .to_axial()
+            })
+            .collect()
+    }
+
+    pub fn to_cube(self) -> Cube {
+        Cube {
+            x: self.q,
+            y: -self.q - self.r,
+            z: self.r,
+        }
+    }
+
+    pub fn to_offset(self) -> Offset {
+        Offset {
+            column: self.q + (self.r - (self.r & 1)) / 2,
+            row: self.r,
+        }
+    }
+}
+
+impl Add for Axial {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            q: self.q + other.q,
+            r: self.r + other.r,
+        }
+    }
+}
+
+impl Sub for Axial {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            q: self.q - other.q,
+            r: self.r - other.r,
+        }
+    }
+}
+
+impl Cube {
+    // Rounds fractional cube coordinates to the tile containing them
+    fn round(x: f64, y: f64, z: f64) -> Self {
+        let (mut rx, mut ry, mut rz) = (x.round(), y.round(), z.round());
+        let (dx, dy, dz) = ((rx - x).abs(), (ry - y).abs(), (rz - z).abs());
+        if dx > dy && dx > dz {
+            rx = -ry - rz;
+        } else if dy > dz {
+            ry = -rx - rz;
+        } else {
+            rz = -rx - ry;
+        }
+
+        Self {
+            x: rx as i32,
+            y: ry as i32,
+            z: rz as i32,
+        }
+    }
+
+    pub fn to_axial(self) -> Axial {
+        Axial {
+            q: self.x,
+            r: self.z,
+        }
+    }
+}
+
+impl Offset {
+    pub fn to_axial(self) -> Axial {
+        Axial {
+            q: self.column - (self.row - (self.row & 1)) / 2,
+            r: self.row,
+        }
+    }
+}
+
+impl fmt::Display for Axial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.q, self.r)
+    }
+}
+
+impl fmt::Display for Cube {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.x, self.y, self.z)
+    }
+}
+
+impl fmt::Display for Offset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.column, self.row)
+    }
+}
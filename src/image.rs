@@ -0,0 +1,161 @@
+use crate::grid::{Coords, Grid};
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Color(pub u8, pub u8, pub u8);
+
+impl Color {
+    fn is_dark(&self) -> bool {
+        let Color(r, g, b) = *self;
+        (r as u32 + g as u32 + b as u32) / 3 < 128
+    }
+}
+
+pub trait ToColor {
+    fn color(&self) -> Color;
+}
+
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Image {
+    // Draws every cell of the grid as a square of `scale` by `scale` pixels
+    pub fn from_grid<T>(grid: &Grid<T>, scale: usize, color: impl Fn(Coords, &T) -> Color) -> Self {
+        let cells = Grid::from_fn(grid.width(), grid.height(), |coords| {
+            color(coords, &grid[coords])
+        });
+        let width = grid.width() * scale;
+        let height = grid.height() * scale;
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| cells[(x / scale, y / scale)])
+            .collect();
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    // Saves the image in the format given by the file extension: png, ppm or pbm
+    pub fn save(&self, path: &str) -> Result<()> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+        let data = match extension.as_deref() {
+            Some("png") => self.png(),
+            Some("ppm") => self.ppm(),
+            Some("pbm") => self.pbm(),
+            _ => bail!("Unsupported image format, expected .png, .ppm or .pbm"),
+        };
+
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(&data)?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    fn ppm(&self) -> Vec<u8> {
+        let mut data = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        data.extend(rgb(&self.pixels));
+        data
+    }
+
+    fn pbm(&self) -> Vec<u8> {
+        let mut data = format!("P4\n{} {}\n", self.width, self.height).into_bytes();
+        for row in self.pixels.chunks(self.width.max(1)) {
+            for byte in row.chunks(8) {
+                data.push(
+                    byte.iter()
+                        .enumerate()
+                        .filter(|(_, color)| color.is_dark())
+                        .fold(0, |bits, (i, _)| bits | (0x80 >> i)),
+                );
+            }
+        }
+        data
+    }
+
+    // Encodes the image as an uncompressed PNG, using stored deflate blocks
+    fn png(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity((self.width * 3 + 1) * self.height);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            raw.push(0);
+            raw.extend(rgb(row));
+        }
+
+        let mut zlib = vec![0x78, 0x01];
+        let mut blocks = raw.chunks(0xffff).peekable();
+        if blocks.peek().is_none() {
+            zlib.extend(&[1, 0, 0, 0xff, 0xff]);
+        }
+        while let Some(block) = blocks.next() {
+            let last = blocks.peek().is_none();
+            let length = block.len() as u16;
+            zlib.push(last as u8);
+            zlib.extend(&length.to_le_bytes());
+            zlib.extend(&(!length).to_le_bytes());
+            zlib.extend(block);
+        }
+        zlib.extend(&adler32(&raw).to_be_bytes());
+
+        let mut header = Vec::new();
+        header.extend(&(self.width as u32).to_be_bytes());
+        header.extend(&(self.height as u32).to_be_bytes());
+        // 8 bit RGB, default compression and filtering, no interlacing
+        header.extend(&[8, 2, 0, 0, 0]);
+
+        let mut data = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+        write_png_chunk(&mut data, b"IHDR", &header);
+        write_png_chunk(&mut data, b"IDAT", &zlib);
+        write_png_chunk(&mut data, b"IEND", &[]);
+        data
+    }
+}
+
+fn rgb(pixels: &[Color]) -> Vec<u8> {
+    pixels
+        .iter()
+        .flat_map(|Color(r, g, b)| vec![*r, *g, *b])
+        .collect()
+}
+
+fn write_png_chunk(data: &mut Vec<u8>, kind: &[u8; 4], content: &[u8]) {
+    data.extend(&(content.len() as u32).to_be_bytes());
+    let start = data.len();
+    data.extend(kind);
+    data.extend(content);
+    let crc = crc32(&data[start..]);
+    data.extend(&crc.to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (a, b) = bytes.iter().fold((1u32, 0u32), |(a, b), byte| {
+        let a = (a + *byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
+}
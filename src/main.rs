@@ -11,7 +11,16 @@ use anyhow::Result;
 use reqwest::blocking::Client;
 use reqwest::header::COOKIE;
 
+mod animation;
+mod automaton;
+mod bigint;
 mod day;
+mod grid;
+mod hex;
+mod image;
+mod matching;
+mod random;
+mod sums;
 
 const DAYS: [Day; 25] = [
     day1::DAY1,
@@ -42,8 +51,8 @@ const DAYS: [Day; 25] = [
 ];
 
 fn main() {
-    let args = env::args();
-    let day_string = args.skip(1).next().expect("Missing day argument");
+    let mut args = env::args().skip(1);
+    let day_string = args.next().expect("Missing day argument");
     let day_num: usize = day_string.parse().expect("Unable to parse day");
     let mut cookie = String::new();
     File::open("cookie")
@@ -51,10 +60,12 @@ fn main() {
         .read_to_string(&mut cookie)
         .expect("Unable to read cookie");
 
-    run_day(&cookie, day_num);
+    let command = args.collect::<Vec<_>>();
+
+    run_day(&cookie, day_num, &command);
 }
 
-fn run_day(cookie: &str, day_num: usize) {
+fn run_day(cookie: &str, day_num: usize, command: &[String]) {
     let day = DAYS.get(day_num - 1).expect("Invalid day");
     let input_result = Client::builder()
         .build()
@@ -71,6 +82,11 @@ fn run_day(cookie: &str, day_num: usize) {
         (day.solver_from_input)(&mut BufReader::new(input)).expect("Unable to parse input");
     println!("--- Day {}: {} ---", day_num, day.title);
     println!();
+    if let Some((name, args)) = command.split_first() {
+        print_solution(solver.command(name, args));
+        return;
+    }
+
     println!("Part 1:");
     let solution = solver.part1();
     print_solution(solution);
@@ -0,0 +1,196 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+use std::hash::Hash;
+use std::iter;
+
+use anyhow::{bail, Result};
+use itertools::Itertools;
+
+pub type Assignment<L, R> = Vec<(L, R)>;
+
+pub enum Solution<L, R> {
+    Unique(Assignment<L, R>),
+    // Two of the possible assignments
+    Ambiguous(Assignment<L, R>, Assignment<L, R>),
+    Unsatisfiable,
+}
+
+impl<L: Display, R: Display + PartialEq> Solution<L, R> {
+    pub fn unique(self) -> Result<Assignment<L, R>> {
+        match self {
+            Self::Unique(assignment) => Ok(assignment),
+            Self::Ambiguous(a, b) => {
+                let differences = a
+                    .iter()
+                    .zip(&b)
+                    .filter(|((_, a), (_, b))| a != b)
+                    .map(|((left, a), (_, b))| format!("{} could be {} or {}", left, a, b))
+                    .join(", ");
+                bail!("Ambiguous assignment: {}", differences)
+            }
+            Self::Unsatisfiable => bail!("No assignment satisfies every constraint"),
+        }
+    }
+}
+
+// Every left item along with its candidates, by index
+#[derive(Clone)]
+struct State {
+    candidates: Vec<BTreeSet<usize>>,
+    assigned: Vec<Option<usize>>,
+}
+
+impl State {
+    // Assigns every left item with only one candidate left, removing that candidate from the
+    // others, until no more can be assigned. The assigned left items are added to `order`, and
+    // false is returned if some left item runs out of candidates
+    fn propagate(&mut self, order: &mut Vec<usize>) -> bool {
+        loop {
+            let single = (0..self.candidates.len())
+                .filter(|left| self.assigned[*left].is_none())
+                .map(|left| (left, self.candidates[left].len()))
+                .find(|(_, count)| *count <= 1);
+            let left = match single {
+                Some((_, 0)) => return false,
+                Some((left, _)) => left,
+                None => return true,
+            };
+
+            let right = *self.candidates[left].iter().next().unwrap();
+            self.assign(left, right);
+            order.push(left);
+        }
+    }
+
+    fn assign(&mut self, left: usize, right: usize) {
+        self.assigned[left] = Some(right);
+        self.candidates[left] = iter::once(right).collect();
+        for (other, candidates) in self.candidates.iter_mut().enumerate() {
+            if other != left {
+                candidates.remove(&right);
+            }
+        }
+    }
+
+    // Finds up to `limit` complete assignments
+    fn search(mut self, limit: usize, found: &mut Vec<Vec<usize>>) {
+        if found.len() >= limit || !self.propagate(&mut Vec::new()) {
+            return;
+        }
+
+        let unassigned = (0..self.candidates.len())
+            .filter(|left| self.assigned[*left].is_none())
+            .min_by_key(|left| self.candidates[*left].len());
+        match unassigned {
+            Some(left) => {
+                for right in self.candidates[left].clone() {
+                    let mut state = self.clone();
+                    state.assign(left, right);
+                    state.search(limit, found);
+                }
+            }
+            None => found.push(self.assigned.iter().map(|right| right.unwrap()).collect()),
+        }
+    }
+}
+
+// The result of only assigning left items with a single candidate left
+pub struct Elimination<L, R> {
+    // In the order they were assigned
+    pub assigned: Assignment<L, R>,
+    // Every left item that could not be assigned, along with its remaining candidates
+    pub remaining: Vec<(L, Vec<R>)>,
+}
+
+// Assigns every left item a distinct right item out of its candidates
+pub struct Matching<L, R> {
+    left: Vec<L>,
+    right: Vec<R>,
+    state: State,
+}
+
+impl<L: Clone, R: Clone + Eq + Hash> Matching<L, R> {
+    pub fn new<C: IntoIterator<Item = R>>(candidates: impl IntoIterator<Item = (L, C)>) -> Self {
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        let mut right_indices = HashMap::new();
+        let candidates = candidates
+            .into_iter()
+            .map(|(item, candidates)| {
+                left.push(item);
+                candidates
+                    .into_iter()
+                    .map(|candidate| {
+                        *right_indices.entry(candidate.clone()).or_insert_with(|| {
+                            right.push(candidate);
+                            right.len() - 1
+                        })
+                    })
+                    .collect()
+            })
+            .collect_vec();
+
+        let assigned = vec![None; candidates.len()];
+        Self {
+            left,
+            right,
+            state: State {
+                candidates,
+                assigned,
+            },
+        }
+    }
+
+    fn assignment(&self, rights: &[usize]) -> Assignment<L, R> {
+        self.left
+            .iter()
+            .cloned()
+            .zip(rights.iter().map(|right| self.right[*right].clone()))
+            .collect()
+    }
+
+    pub fn eliminate(&self) -> Elimination<L, R> {
+        let mut state = self.state.clone();
+        let mut order = Vec::new();
+        state.propagate(&mut order);
+
+        let assigned = order
+            .iter()
+            .map(|left| {
+                let right = state.assigned[*left].unwrap();
+                (self.left[*left].clone(), self.right[right].clone())
+            })
+            .collect();
+        let remaining = (0..self.left.len())
+            .filter(|left| state.assigned[*left].is_none())
+            .map(|left| {
+                let candidates = state.candidates[left]
+                    .iter()
+                    .map(|right| self.right[*right].clone())
+                    .collect();
+                (self.left[left].clone(), candidates)
+            })
+            .collect();
+
+        Elimination {
+            assigned,
+            remaining,
+        }
+    }
+
+    // Up to `limit` of the possible assignments
+    pub fn assignments(&self, limit: usize) -> Vec<Assignment<L, R>> {
+        let mut found = Vec::new();
+        self.state.clone().search(limit, &mut found);
+        found.iter().map(|rights| self.assignment(rights)).collect()
+    }
+
+    pub fn solve(&self) -> Solution<L, R> {
+        let mut assignments = self.assignments(2).into_iter();
+        match (assignments.next(), assignments.next()) {
+            (None, _) => Solution::Unsatisfiable,
+            (Some(assignment), None) => Solution::Unique(assignment),
+            (Some(a), Some(b)) => Solution::Ambiguous(a, b),
+        }
+    }
+}
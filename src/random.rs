@@ -0,0 +1,25 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// A small xorshift* pseudorandom number generator, good enough for sampling puzzle solutions
+pub struct Random(u64);
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        // The state must never be zero
+        Self((seed ^ 0x9e37_79b9_7f4a_7c15).max(1))
+    }
+
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64);
+        Self::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
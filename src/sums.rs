@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use itertools::Itertools;
+
+// Indices of k >= 1 numbers at distinct positions adding up to the target, taking O(n^(k-1))
+// time for k >= 2
+pub fn k_sum(numbers: &[u64], k: usize, target: u64) -> Option<Vec<usize>> {
+    fn search(sorted: &[(u64, usize)], k: usize, target: u64, chosen: &mut Vec<usize>) -> bool {
+        match k {
+            1 => match sorted.binary_search_by_key(&target, |(n, _)| *n) {
+                Ok(i) => {
+                    chosen.push(sorted[i].1);
+                    true
+                }
+                Err(_) => false,
+            },
+            2 => {
+                if sorted.is_empty() {
+                    return false;
+                }
+
+                let (mut low, mut high) = (0, sorted.len() - 1);
+                while low < high {
+                    let sum = sorted[low].0 + sorted[high].0;
+                    if sum < target {
+                        low += 1;
+                    } else if sum > target {
+                        high -= 1;
+                    } else {
+                        chosen.push(sorted[low].1);
+                        chosen.push(sorted[high].1);
+                        return true;
+                    }
+                }
+                false
+            }
+            _ => {
+                for (i, (n, index)) in sorted.iter().enumerate() {
+                    // The rest of the numbers are at least as large
+                    if n.saturating_mul(k as u64) > target {
+                        break;
+                    }
+
+                    chosen.push(*index);
+                    if search(&sorted[(i + 1)..], k - 1, target - n, chosen) {
+                        return true;
+                    }
+                    chosen.pop();
+                }
+                false
+            }
+        }
+    }
+
+    if k == 0 {
+        return None;
+    }
+
+    let sorted = numbers
+        .iter()
+        .copied()
+        .enumerate()
+        .map(|(i, n)| (n, i))
+        .sorted()
+        .collect_vec();
+    let mut chosen = Vec::new();
+    if search(&sorted, k, target, &mut chosen) {
+        chosen.sort_unstable();
+        Some(chosen)
+    } else {
+        None
+    }
+}
+
+// Positions of the numbers that are not the sum of two numbers at distinct positions among the
+// `window` numbers before them
+pub fn invalid_positions(numbers: &[u64], window: usize) -> Vec<usize> {
+    if window == 0 {
+        return (0..numbers.len()).collect();
+    }
+
+    let mut counts = HashMap::<u64, usize>::new();
+    let mut invalid = Vec::new();
+    for (i, n) in numbers.iter().enumerate() {
+        if i >= window {
+            let valid = counts.iter().any(|(a, count)| match n.checked_sub(*a) {
+                Some(b) if b == *a => *count >= 2,
+                Some(b) => counts.contains_key(&b),
+                None => false,
+            });
+            if !valid {
+                invalid.push(i);
+            }
+
+            let oldest = numbers[i - window];
+            let count = counts.get_mut(&oldest).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&oldest);
+            }
+        }
+        *counts.entry(*n).or_insert(0) += 1;
+    }
+
+    invalid
+}
+
+// The first contiguous run of at least `min_length` numbers adding up to the target, by where it
+// ends, in linear time
+pub fn subarray_with_sum(numbers: &[u64], target: u64, min_length: usize) -> Option<Range<usize>> {
+    let mut start = 0;
+    let mut sum = 0;
+    for (end, n) in numbers.iter().enumerate() {
+        sum += n;
+        while sum > target {
+            sum -= numbers[start];
+            start += 1;
+        }
+
+        if sum == target && end + 1 - start >= min_length {
+            return Some(start..(end + 1));
+        }
+    }
+
+    None
+}